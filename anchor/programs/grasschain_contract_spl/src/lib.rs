@@ -2,65 +2,365 @@ use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
     metadata::{
-        create_metadata_accounts_v3, create_master_edition_v3,
-        CreateMetadataAccountsV3, CreateMasterEditionV3,
+        create_metadata_accounts_v3, create_master_edition_v3, verify_sized_collection_item,
+        CreateMetadataAccountsV3, CreateMasterEditionV3, VerifySizedCollectionItem,
     },
-    token::{self, Mint, Token, TokenAccount, Transfer},
+    token::{self, Approve, FreezeAccount, Mint, Revoke, Token, TokenAccount, Transfer},
 };
-use mpl_token_metadata::types::DataV2;
+use anchor_lang::solana_program::program_option::COption;
+use mpl_token_metadata::types::{Collection, CollectionDetails, Creator, DataV2};
 use mpl_token_metadata::ID as token_metadata_program_id; // This is a constant Pubkey
-use std::str::FromStr;
 
+pub mod math;
+use math::Rounding;
 
 declare_id!("BfEoJTm7VLRvynukHU2Jjf9gnqWPF7pz9R43MrFNn4cg");
 
 // Constants
-const USDC_MINT: &str = "Gh9ZwEmdLJ8DscKNTkTqPbNwLNNBjuSzaG9Vp2KGtKJr";
-const ADMIN_ADDRESS: &str = "74bwEVrLxoWtg8ya7gB1KKKuff9wnNADys1Ss1cxsEdd";
+const MAX_ALLOWED_MINTS: usize = 4;
+const MAX_FEE_BPS: u16 = 10_000;
+
+// Role seeds: a RoleAssignment PDA at ["role", <seed>, member] grants that role
+const ROLE_ORIGINATOR_SEED: &[u8] = b"originator";
+const ROLE_VERIFIER_SEED: &[u8] = b"verifier";
+const ROLE_TREASURER_SEED: &[u8] = b"treasurer";
+const ROLE_PAUSER_SEED: &[u8] = b"pauser";
+
+// Withdrawal multisig
+const MAX_MULTISIG_SIGNERS: usize = 10;
+const WITHDRAW_PROPOSAL_TTL: i64 = 3 * 86400; // 3 days
+const MULTISIG_CHANGE_TTL: i64 = 3 * 86400; // 3 days
+
+// Protocol-wide bounds for the per-contract timing windows (seconds)
+const MIN_WINDOW_SECONDS: i64 = 86400; // 1 day
+const MAX_WINDOW_SECONDS: i64 = 180 * 86400; // ~6 months
+
+// Investor-approved prolongations
+const MAX_PROLONGATIONS: u8 = 3;
+const PROLONGATION_VOTING_WINDOW: i64 = 3 * 86400;
+const MAX_SWEETENER_BPS: u16 = 2_000;
+
+// Withdrawals while funding
+const MAX_EXIT_FEE_BPS: u16 = 500; // 5% of the amount withdrawn
+
+// Position NFTs (Metaplex field limits)
+const POSITION_NFT_SYMBOL: &str = "PTORA";
+const MAX_NFT_NAME_LEN: usize = 32;
+const MAX_NFT_URI_LEN: usize = 200;
+
+// Installment schedules
+const MAX_INSTALLMENTS: usize = 24;
+
+// Yield: annual rate in basis points, accrued Actual/365 (see math.rs)
+const MAX_APR_BPS: u16 = 10_000; // 100% APR
+const MAX_PREPAYMENT_PENALTY_BPS: u16 = 2_000; // 20% of principal
+const MAX_LATE_PENALTY_BPS_PER_DAY: u16 = 100; // 1% of principal per day
 
 
 #[program]
 pub mod grasschain_contract_spl {
     use super::*;
 
+    /// (0) Program upgrade authority creates the singleton Config
+    pub fn initialize_config(
+        ctx: Context<InitializeConfig>,
+        admin: Pubkey,
+        allowed_mints: Vec<Pubkey>,
+        fee_bps: u16,
+        fee_recipient: Pubkey,
+    ) -> Result<()> {
+        require!(allowed_mints.len() <= MAX_ALLOWED_MINTS, ErrorCode::TooManyMints);
+        require!(fee_bps <= MAX_FEE_BPS, ErrorCode::InvalidFee);
+
+        let config = &mut ctx.accounts.config;
+        config.admin = admin;
+        config.pending_admin = Pubkey::default();
+        config.allowed_mints = allowed_mints;
+        config.fee_bps = fee_bps;
+        config.fee_recipient = fee_recipient;
+        config.paused = false;
+        config.bump = ctx.bumps.config;
+        Ok(())
+    }
+
+    /// (0b) Admin updates mints and fees (pausing goes through `pause_protocol`)
+    pub fn update_config(
+        ctx: Context<UpdateConfig>,
+        allowed_mints: Vec<Pubkey>,
+        fee_bps: u16,
+        fee_recipient: Pubkey,
+    ) -> Result<()> {
+        require!(allowed_mints.len() <= MAX_ALLOWED_MINTS, ErrorCode::TooManyMints);
+        require!(fee_bps <= MAX_FEE_BPS, ErrorCode::InvalidFee);
+
+        let config = &mut ctx.accounts.config;
+        config.allowed_mints = allowed_mints;
+        config.fee_bps = fee_bps;
+        config.fee_recipient = fee_recipient;
+        Ok(())
+    }
+
+    /// (0c) Admin nominates a successor; nothing changes until they accept
+    pub fn propose_admin(ctx: Context<UpdateConfig>, new_admin: Pubkey) -> Result<()> {
+        ctx.accounts.config.pending_admin = new_admin;
+        Ok(())
+    }
+
+    /// (0d) Nominated admin takes over
+    pub fn accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.admin = config.pending_admin;
+        config.pending_admin = Pubkey::default();
+        Ok(())
+    }
+
+    /// (0e) Admin grants `role` to `member`
+    pub fn grant_role(ctx: Context<GrantRole>, role: Role, member: Pubkey) -> Result<()> {
+        let assignment = &mut ctx.accounts.role_assignment;
+        assignment.role = role;
+        assignment.member = member;
+        assignment.bump = ctx.bumps.role_assignment;
+        Ok(())
+    }
+
+    /// (0f) Admin revokes `role` from `member` (closes the RoleAssignment)
+    pub fn revoke_role(_ctx: Context<RevokeRole>, _role: Role, _member: Pubkey) -> Result<()> {
+        Ok(())
+    }
+
+    /// (0g) Pauser halts the whole protocol; refunds and withdrawals stay open
+    pub fn pause_protocol(ctx: Context<PauseProtocol>, reason: String) -> Result<()> {
+        ctx.accounts.config.paused = true;
+        emit!(PauseChanged {
+            contract: None,
+            paused: true,
+            by: ctx.accounts.pauser.key(),
+            reason,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    pub fn unpause_protocol(ctx: Context<PauseProtocol>, reason: String) -> Result<()> {
+        ctx.accounts.config.paused = false;
+        emit!(PauseChanged {
+            contract: None,
+            paused: false,
+            by: ctx.accounts.pauser.key(),
+            reason,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// (0g') Pauser halts a single contract
+    pub fn pause_contract(ctx: Context<PauseContract>, reason: String) -> Result<()> {
+        ctx.accounts.contract.paused = true;
+        emit!(PauseChanged {
+            contract: Some(ctx.accounts.contract.key()),
+            paused: true,
+            by: ctx.accounts.pauser.key(),
+            reason,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    pub fn unpause_contract(ctx: Context<PauseContract>, reason: String) -> Result<()> {
+        ctx.accounts.contract.paused = false;
+        emit!(PauseChanged {
+            contract: Some(ctx.accounts.contract.key()),
+            paused: false,
+            by: ctx.accounts.pauser.key(),
+            reason,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// (0h) Admin registers the initial M-of-N signers that approve escrow withdrawals.
+    /// One-time: later changes need the current multisig (see propose_multisig_change)
+    pub fn set_multisig(ctx: Context<SetMultisig>, signers: Vec<Pubkey>, threshold: u8) -> Result<()> {
+        Multisig::validate(&signers, threshold)?;
+
+        let multisig = &mut ctx.accounts.multisig;
+        multisig.signers = signers;
+        multisig.threshold = threshold;
+        multisig.bump = ctx.bumps.multisig;
+        Ok(())
+    }
+
+    /// (0i) A current multisig signer proposes a new signer set and threshold
+    pub fn propose_multisig_change(
+        ctx: Context<ProposeMultisigChange>,
+        signers: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        let proposer = ctx.accounts.proposer.key();
+        let clock = Clock::get()?;
+        require!(
+            ctx.accounts.multisig.signers.contains(&proposer),
+            ErrorCode::Unauthorized
+        );
+        Multisig::validate(&signers, threshold)?;
+
+        let change = &mut ctx.accounts.change;
+        change.proposer = proposer;
+        change.signers = signers;
+        change.threshold = threshold;
+        change.approvals = Vec::new();
+        change.expires_at = clock.unix_timestamp + MULTISIG_CHANGE_TTL;
+        change.bump = ctx.bumps.change;
+        Ok(())
+    }
+
+    /// (0j) A current multisig signer approves the pending change
+    pub fn approve_multisig_change(ctx: Context<ApproveMultisigChange>) -> Result<()> {
+        let signer = ctx.accounts.signer.key();
+        let change = &mut ctx.accounts.change;
+        let clock = Clock::get()?;
+
+        require!(
+            ctx.accounts.multisig.signers.contains(&signer),
+            ErrorCode::Unauthorized
+        );
+        require!(
+            clock.unix_timestamp <= change.expires_at,
+            ErrorCode::ProposalExpired
+        );
+        require!(
+            !change.approvals.contains(&signer),
+            ErrorCode::AlreadyApproved
+        );
+
+        change.approvals.push(signer);
+        Ok(())
+    }
+
+    /// (0k) Anyone applies a change once the current multisig's threshold approved it
+    pub fn execute_multisig_change(ctx: Context<ExecuteMultisigChange>) -> Result<()> {
+        let change = &ctx.accounts.change;
+        let multisig = &mut ctx.accounts.multisig;
+        let clock = Clock::get()?;
+
+        require!(
+            clock.unix_timestamp <= change.expires_at,
+            ErrorCode::ProposalExpired
+        );
+        let approvals = change
+            .approvals
+            .iter()
+            .filter(|k| multisig.signers.contains(k))
+            .count();
+        require!(
+            approvals >= multisig.threshold as usize,
+            ErrorCode::NotEnoughApprovals
+        );
+
+        multisig.signers = change.signers.clone();
+        multisig.threshold = change.threshold;
+        Ok(())
+    }
+
+    /// (0l) Proposer can drop a pending change at any time; anyone can clear an expired one
+    pub fn cancel_multisig_change(ctx: Context<CancelMultisigChange>) -> Result<()> {
+        let change = &ctx.accounts.change;
+        let clock = Clock::get()?;
+
+        require!(
+            ctx.accounts.caller.key() == change.proposer
+                || clock.unix_timestamp > change.expires_at,
+            ErrorCode::Unauthorized
+        );
+        Ok(())
+    }
+
     /// (1) Admin creates a contract => status=Created => 1 month to fill
   /// (1) Admin creates a contract (status = Created) with an off–chain image URL.
+  #[allow(clippy::too_many_arguments)]
   pub fn create_contract(
     ctx: Context<CreateContract>,
     total_investment_needed: u64,
-    yield_percentage: i64,
+    apr_bps: u16,
     duration_in_seconds: i64,
     contract_id: u64,
-    nft_mint: Pubkey,
     farm_name: String,
     farm_address: String,
     farm_image_url: String, // NEW field – a URL (from Blob)
+    funding_window: i64,
+    admin_window: i64,
+    buyback_window: i64,
+    prolong_extension: i64,
+    prepayment_policy: PrepaymentPolicy,
+    prepayment_penalty_bps: u16,
+    late_penalty_bps_per_day: u16,
+    minimum_raise: u64,
 ) -> Result<()> {
     let contract = &mut ctx.accounts.contract;
 
-    // Check USDC mint
+    // 0 keeps the raise all-or-nothing
+    require!(
+        minimum_raise <= total_investment_needed,
+        ErrorCode::InvalidMinimumRaise
+    );
+
+    require!(apr_bps <= MAX_APR_BPS, ErrorCode::InvalidRate);
+    require!(
+        prepayment_penalty_bps <= MAX_PREPAYMENT_PENALTY_BPS,
+        ErrorCode::InvalidRate
+    );
+    require!(
+        late_penalty_bps_per_day <= MAX_LATE_PENALTY_BPS_PER_DAY,
+        ErrorCode::InvalidRate
+    );
+    for window in [funding_window, admin_window, buyback_window, prolong_extension] {
+        require!(
+            (MIN_WINDOW_SECONDS..=MAX_WINDOW_SECONDS).contains(&window),
+            ErrorCode::InvalidWindow
+        );
+    }
+
+    // Becomes the position NFT uri, so it has to fit Metaplex's limit
+    require!(
+        farm_image_url.len() <= MAX_NFT_URI_LEN,
+        ErrorCode::InvalidMetadata
+    );
+
+    // Check the mint is one the protocol accepts
     require!(
-        ctx.accounts.token_mint.key() == Pubkey::from_str(USDC_MINT).unwrap(),
+        ctx.accounts.config.allowed_mints.contains(&ctx.accounts.token_mint.key()),
         ErrorCode::InvalidTokenMint
     );
 
     // Initialize the contract data
     contract.admin = ctx.accounts.admin.key();
+    // The farmer co-signs creation, accepting the terms on-chain
+    contract.borrower = ctx.accounts.borrower.key();
     contract.token_mint = ctx.accounts.token_mint.key();
-    contract.nft_mint = nft_mint;
+    // Collection every position NFT of this contract is verified into
+    contract.nft_mint = ctx.accounts.collection_mint.key();
     contract.escrow_token_account = ctx.accounts.escrow_vault.key();
 
     contract.total_investment_needed = total_investment_needed as i64;
     contract.amount_funded_so_far = 0;
-    contract.yield_percentage = yield_percentage;
+    contract.apr_bps = apr_bps;
     contract.duration = duration_in_seconds;
     contract.contract_id = contract_id;
     contract.status = ContractStatus::Created;
 
+    contract.funding_window = funding_window;
+    contract.admin_window = admin_window;
+    contract.buyback_window = buyback_window;
+    contract.prolong_extension = prolong_extension;
+    contract.prepayment_policy = prepayment_policy;
+    contract.prepayment_penalty_bps = prepayment_penalty_bps;
+    contract.early_repaid_at = 0;
+    contract.late_penalty_bps_per_day = late_penalty_bps_per_day;
+    contract.minimum_raise = minimum_raise;
+
     let clock = Clock::get()?;
     contract.upload_date = clock.unix_timestamp;
-    // Funding window: 1 month from upload
-    contract.funding_deadline = clock.unix_timestamp + 30 * 86400;
+    contract.funding_deadline = clock.unix_timestamp + funding_window;
 
     contract.start_time = 0;
     contract.funded_time = 0;
@@ -71,13 +371,162 @@ pub mod grasschain_contract_spl {
     contract.farm_address = farm_address;
     contract.farm_image_url = farm_image_url;
 
+    // Collection NFT: contract PDA is authority and verified creator, the
+    // single token sits in the contract's own ATA
+    let contract = &ctx.accounts.contract;
+    let seeds = &[
+        b"contract",
+        contract.admin.as_ref(),
+        &contract.contract_id.to_le_bytes(),
+        &[ctx.bumps.contract],
+    ];
+    let signer = &[&seeds[..]];
+
+    let mint_to_ctx = CpiContext::new_with_signer(
+        ctx.accounts.token_program.to_account_info(),
+        token::MintTo {
+            mint: ctx.accounts.collection_mint.to_account_info(),
+            to: ctx.accounts.collection_token_account.to_account_info(),
+            authority: contract.to_account_info(),
+        },
+        signer,
+    );
+    token::mint_to(mint_to_ctx, 1)?;
+
+    let metadata_ctx = CpiContext::new_with_signer(
+        ctx.accounts.token_metadata_program.to_account_info(),
+        CreateMetadataAccountsV3 {
+            metadata: ctx.accounts.collection_metadata.to_account_info(),
+            mint: ctx.accounts.collection_mint.to_account_info(),
+            mint_authority: contract.to_account_info(),
+            update_authority: contract.to_account_info(),
+            payer: ctx.accounts.admin.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            rent: ctx.accounts.rent.to_account_info(),
+        },
+        signer,
+    );
+    let data_v2 = DataV2 {
+        name: contract.position_nft_name(),
+        symbol: POSITION_NFT_SYMBOL.to_string(),
+        uri: contract.farm_image_url.clone(),
+        seller_fee_basis_points: 0,
+        creators: Some(position_nft_creators(contract.key())),
+        collection: None,
+        uses: None,
+    };
+    create_metadata_accounts_v3(
+        metadata_ctx,
+        data_v2,
+        true,
+        true,
+        Some(CollectionDetails::V1 { size: 0 }),
+    )?;
+
+    let master_edition_ctx = CpiContext::new_with_signer(
+        ctx.accounts.token_metadata_program.to_account_info(),
+        CreateMasterEditionV3 {
+            edition: ctx.accounts.collection_master_edition.to_account_info(),
+            mint: ctx.accounts.collection_mint.to_account_info(),
+            update_authority: contract.to_account_info(),
+            mint_authority: contract.to_account_info(),
+            payer: ctx.accounts.admin.to_account_info(),
+            metadata: ctx.accounts.collection_metadata.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            rent: ctx.accounts.rent.to_account_info(),
+        },
+        signer,
+    );
+    create_master_edition_v3(master_edition_ctx, Some(0))?;
+
     Ok(())
 }
 
+    /// (1b) Originator and borrower attach an installment schedule before anyone invests
+    pub fn set_repayment_schedule(
+        ctx: Context<SetRepaymentSchedule>,
+        installments: Vec<InstallmentTerms>,
+    ) -> Result<()> {
+        let contract = &mut ctx.accounts.contract;
+
+        require!(
+            contract.status == ContractStatus::Created && contract.amount_funded_so_far == 0,
+            ErrorCode::InvalidContractStatus
+        );
+        // Installments are fixed against the full target and can't shrink with the raise
+        require!(contract.minimum_raise == 0, ErrorCode::InvalidSchedule);
+        require!(
+            !installments.is_empty() && installments.len() <= MAX_INSTALLMENTS,
+            ErrorCode::InvalidSchedule
+        );
+
+        let mut total_scheduled = 0u64;
+        let mut last_offset = 0i64;
+        for terms in installments.iter() {
+            require!(
+                terms.due_offset > last_offset && terms.amount > 0,
+                ErrorCode::InvalidSchedule
+            );
+            last_offset = terms.due_offset;
+            total_scheduled = math::add(total_scheduled, terms.amount)?;
+        }
+        // Balloon no later than maturity, and at least the principal comes back
+        require!(last_offset <= contract.duration, ErrorCode::InvalidSchedule);
+        require!(
+            total_scheduled >= contract.total_investment_needed as u64,
+            ErrorCode::InvalidSchedule
+        );
+
+        let schedule = &mut ctx.accounts.schedule;
+        schedule.contract = contract.key();
+        schedule.installments = installments
+            .into_iter()
+            .map(|terms| Installment {
+                due_offset: terms.due_offset,
+                amount: terms.amount,
+                paid: 0,
+                missed: false,
+            })
+            .collect();
+        schedule.total_scheduled = total_scheduled;
+        schedule.total_paid = 0;
+        schedule.missed_count = 0;
+        schedule.bump = ctx.bumps.schedule;
+
+        contract.has_schedule = true;
+        Ok(())
+    }
+
+    /// (1c) Originator and borrower set the investor withdrawal terms before anyone invests:
+    /// free exit for `cooloff_seconds` after each investment, `exit_fee_bps` after that
+    pub fn set_withdrawal_terms(
+        ctx: Context<SetWithdrawalTerms>,
+        cooloff_seconds: i64,
+        exit_fee_bps: u16,
+    ) -> Result<()> {
+        let contract = &mut ctx.accounts.contract;
+
+        require!(
+            contract.status == ContractStatus::Created && contract.amount_funded_so_far == 0,
+            ErrorCode::InvalidContractStatus
+        );
+        require!(
+            (0..=MAX_WINDOW_SECONDS).contains(&cooloff_seconds),
+            ErrorCode::InvalidWindow
+        );
+        require!(exit_fee_bps <= MAX_EXIT_FEE_BPS, ErrorCode::InvalidFee);
+
+        contract.withdrawal_cooloff = cooloff_seconds;
+        contract.exit_fee_bps = exit_fee_bps;
+        Ok(())
+    }
+
 /// (2) Investor invests a partial amount.
 pub fn invest_contract(ctx: Context<InvestContract>, amount: u64) -> Result<()> {
     let contract = &mut ctx.accounts.contract;
     let clock = Clock::get()?;
+    require!(!ctx.accounts.config.is_paused(contract), ErrorCode::Paused);
     require!(
         contract.status == ContractStatus::Created || contract.status == ContractStatus::Funding,
         ErrorCode::InvalidContractStatus
@@ -88,7 +537,7 @@ pub fn invest_contract(ctx: Context<InvestContract>, amount: u64) -> Result<()>
     );
     let needed = contract.total_investment_needed as u64;
     require!(
-        contract.amount_funded_so_far + amount <= needed,
+        math::add(contract.amount_funded_so_far, amount)? <= needed,
         ErrorCode::ExceedsContractNeed
     );
     let cpi_ctx = CpiContext::new(
@@ -100,83 +549,208 @@ pub fn invest_contract(ctx: Context<InvestContract>, amount: u64) -> Result<()>
         },
     );
     token::transfer(cpi_ctx, amount)?;
-    contract.amount_funded_so_far += amount;
+    contract.amount_funded_so_far = math::add(contract.amount_funded_so_far, amount)?;
     let record = &mut ctx.accounts.investor_record;
+    if record.amount == 0 {
+        // Fresh record, or a wallet coming back after withdrawing everything
+        contract.investor_count += 1;
+    }
     record.contract = contract.key();
     record.investor = ctx.accounts.investor.key();
-    record.amount += amount;
+    record.amount = math::add(record.amount, amount)?;
     record.bump = ctx.bumps.investor_record;
+    record.add_to_cooloff(amount, clock.unix_timestamp, contract.withdrawal_cooloff)?;
 
-    contract.status = ContractStatus::Funding;
+    // Final investment closes the raise; the verification window starts now
+    if contract.amount_funded_so_far == needed {
+        contract.status = ContractStatus::FundedPendingVerification;
+        contract.funded_time = clock.unix_timestamp;
+    } else {
+        contract.status = ContractStatus::Funding;
+    }
         Ok(())
     }
 
-    pub fn claim_nft(
-        ctx: Context<ClaimNft>,
-        name: String,
-        symbol: String,
-        uri: String,
-    ) -> Result<()> {
+    /// (2a) Investor pulls (part of) their principal back out while the raise is open.
+    /// Past the cool-off the exit fee goes to the protocol fee recipient.
+    pub fn withdraw_investment(ctx: Context<WithdrawInvestment>, amount: u64) -> Result<()> {
+        let contract = &mut ctx.accounts.contract;
+        let record = &mut ctx.accounts.investor_record;
+        let clock = Clock::get()?;
+        // Not gated on pause: like claim_refund, investors can always take their principal back
+
+        require!(
+            contract.status == ContractStatus::Created || contract.status == ContractStatus::Funding,
+            ErrorCode::InvalidContractStatus
+        );
+        require!(
+            clock.unix_timestamp <= contract.funding_deadline,
+            ErrorCode::FundingWindowExpired
+        );
+        require!(
+            amount > 0 && amount <= record.amount,
+            ErrorCode::InsufficientFunds
+        );
+
+        let fee = record.take_withdrawal_fee(
+            amount,
+            clock.unix_timestamp,
+            contract.withdrawal_cooloff,
+            contract.exit_fee_bps,
+        )?;
+
+        let seeds = &[
+            b"contract",
+            contract.admin.as_ref(),
+            &contract.contract_id.to_le_bytes(),
+            &[ctx.bumps.contract],
+        ];
+        let signer = &[&seeds[..]];
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.escrow_vault.to_account_info(),
+                to: ctx.accounts.investor_token_account.to_account_info(),
+                authority: contract.to_account_info(),
+            },
+            signer,
+        );
+        token::transfer(cpi_ctx, math::sub(amount, fee)?)?;
+
+        if fee > 0 {
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.escrow_vault.to_account_info(),
+                    to: ctx.accounts.fee_recipient_token_account.to_account_info(),
+                    authority: contract.to_account_info(),
+                },
+                signer,
+            );
+            token::transfer(cpi_ctx, fee)?;
+        }
+
+        record.amount = math::sub(record.amount, amount)?;
+        contract.amount_funded_so_far = math::sub(contract.amount_funded_so_far, amount)?;
+        if record.amount == 0 {
+            // Fully out; invest_contract counts the wallet again if it returns
+            contract.investor_count -= 1;
+        }
+        if contract.amount_funded_so_far == 0 {
+            contract.status = ContractStatus::Created;
+        }
+        Ok(())
+    }
+
+    /// Investor mints the NFT for their position once the contract is live.
+    /// The contract PDA is mint, freeze and update authority; metadata comes from the contract.
+    pub fn claim_nft(ctx: Context<ClaimNft>) -> Result<()> {
+        let contract = &ctx.accounts.contract;
         let investor_record = &mut ctx.accounts.investor_record;
+        require!(!ctx.accounts.config.is_paused(contract), ErrorCode::Paused);
+
+        // Positions only mean something once the raise is final and disbursed
+        require!(
+            matches!(
+                contract.status,
+                ContractStatus::Active | ContractStatus::PendingBuyback | ContractStatus::Prolonged
+            ),
+            ErrorCode::InvalidContractStatus
+        );
+        require!(investor_record.amount > 0, ErrorCode::AlreadySettled);
         
         // 1) Check that the investor has not already claimed the NFT.
         if investor_record.nft_minted {
             return err!(ErrorCode::NftAlreadyClaimed);
         }
+
+        let seeds = &[
+            b"contract",
+            contract.admin.as_ref(),
+            &contract.contract_id.to_le_bytes(),
+            &[ctx.bumps.contract],
+        ];
+        let signer = &[&seeds[..]];
         
         // 2) Mint 1 token to the investor's associated token account.
-        let mint_to_ctx = CpiContext::new(
+        let mint_to_ctx = CpiContext::new_with_signer(
             ctx.accounts.token_program.to_account_info(),
             token::MintTo {
                 mint: ctx.accounts.mint.to_account_info(),
                 to: ctx.accounts.associated_token_account.to_account_info(),
-                authority: ctx.accounts.investor.to_account_info(),
+                authority: contract.to_account_info(),
             },
+            signer,
         );
         token::mint_to(mint_to_ctx, 1)?;
         
         // 3) Create the metadata account via the Metaplex token metadata CPI.
-        let metadata_ctx = CpiContext::new(
+        let metadata_ctx = CpiContext::new_with_signer(
             ctx.accounts.token_metadata_program.to_account_info(),
             CreateMetadataAccountsV3 {
                 metadata: ctx.accounts.metadata_account.to_account_info(),
                 mint: ctx.accounts.mint.to_account_info(),
-                mint_authority: ctx.accounts.investor.to_account_info(),
-                update_authority: ctx.accounts.investor.to_account_info(),
+                mint_authority: contract.to_account_info(),
+                update_authority: contract.to_account_info(),
                 payer: ctx.accounts.investor.to_account_info(),
                 system_program: ctx.accounts.system_program.to_account_info(),
                 rent: ctx.accounts.rent.to_account_info(),
             },
+            signer,
         );
         let data_v2 = DataV2 {
-            name,
-            symbol,
-            uri,
+            name: contract.position_nft_name(),
+            symbol: POSITION_NFT_SYMBOL.to_string(),
+            uri: contract.farm_image_url.clone(),
             seller_fee_basis_points: 0, // Set royalties as needed
-            creators: None,
-            collection: None,
+            creators: Some(position_nft_creators(contract.key())),
+            // Verified below; Metaplex only accepts unverified here
+            collection: Some(Collection {
+                verified: false,
+                key: contract.nft_mint,
+            }),
             uses: None,
         };
         create_metadata_accounts_v3(metadata_ctx, data_v2, false, true, None)?;
         
-        // 4) Create the master edition account (this disables print editions if max_supply is None)
-        let master_edition_ctx = CpiContext::new(
+        // 4) Create the master edition account; max_supply 0 => no prints of a position
+        let master_edition_ctx = CpiContext::new_with_signer(
             ctx.accounts.token_metadata_program.to_account_info(),
             CreateMasterEditionV3 {
                 edition: ctx.accounts.master_edition_account.to_account_info(),
                 mint: ctx.accounts.mint.to_account_info(),
-                update_authority: ctx.accounts.investor.to_account_info(),
-                mint_authority: ctx.accounts.investor.to_account_info(),
+                update_authority: contract.to_account_info(),
+                mint_authority: contract.to_account_info(),
                 payer: ctx.accounts.investor.to_account_info(),
                 metadata: ctx.accounts.metadata_account.to_account_info(),
                 token_program: ctx.accounts.token_program.to_account_info(),
                 system_program: ctx.accounts.system_program.to_account_info(),
                 rent: ctx.accounts.rent.to_account_info(),
             },
+            signer,
+        );
+        create_master_edition_v3(master_edition_ctx, Some(0))?;
+
+        // 5) Verify membership of the contract's collection
+        let verify_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_metadata_program.to_account_info(),
+            VerifySizedCollectionItem {
+                payer: ctx.accounts.investor.to_account_info(),
+                metadata: ctx.accounts.metadata_account.to_account_info(),
+                collection_authority: contract.to_account_info(),
+                collection_mint: ctx.accounts.collection_mint.to_account_info(),
+                collection_metadata: ctx.accounts.collection_metadata.to_account_info(),
+                collection_master_edition: ctx
+                    .accounts
+                    .collection_master_edition
+                    .to_account_info(),
+            },
+            signer,
         );
-        create_master_edition_v3(master_edition_ctx, None)?;
+        verify_sized_collection_item(verify_ctx, None)?;
         
-        // 5) Mark that the NFT has been claimed in the investor record.
+        // 6) Mark that the NFT has been claimed in the investor record.
         investor_record.nft_minted = true;
         investor_record.nft_mint = ctx.accounts.mint.key();
         
@@ -185,20 +759,35 @@ pub fn invest_contract(ctx: Context<InvestContract>, amount: u64) -> Result<()>
     
     pub fn verify_funding(ctx: Context<VerifyFunding>) -> Result<()> {
         let contract = &mut ctx.accounts.contract;
-        // only allow if fully funded and still in Funding
+        let clock = Clock::get()?;
+        // invest_contract moves a full raise to FundedPendingVerification itself;
+        // anything still in Funding is short of the target and may only proceed
+        // once the raise has closed above the soft cap
+        if contract.status == ContractStatus::Funding {
             require!(
-                contract.status == ContractStatus::Funding,
-                ErrorCode::InvalidContractStatus
+                clock.unix_timestamp > contract.funding_deadline && contract.soft_cap_met(),
+                ErrorCode::FundingIncomplete
             );
-        let clock = Clock::get()?;
-        contract.status = ContractStatus::FundedPendingVerification;
-        contract.funded_time = clock.unix_timestamp;
+            contract.status = ContractStatus::FundedPendingVerification;
+            contract.funded_time = clock.unix_timestamp;
+        }
+        require!(
+            contract.status == ContractStatus::FundedPendingVerification,
+            ErrorCode::InvalidContractStatus
+        );
+        require!(!contract.verified, ErrorCode::AlreadyVerified);
+        require!(
+            clock.unix_timestamp - contract.funded_time <= contract.admin_window,
+            ErrorCode::AdminWindowExpired
+        );
+
+        contract.verified = true;
         Ok(())
     }
 
 
-    /// (2b) Expire funding if not fully funded by the 1-month deadline => refunds each investor
-    /// This is optional, only if you want to forcibly end the contract if not enough invests
+    /// (2b) Expire funding if not fully funded by the funding deadline => refunds each investor,
+    /// unless the raise cleared the soft cap, in which case it proceeds with what was raised
     pub fn expire_funding(ctx: Context<ExpireFunding>) -> Result<()> {
         let contract = &mut ctx.accounts.contract;
         let clock = Clock::get()?;
@@ -216,20 +805,21 @@ pub fn invest_contract(ctx: Context<InvestContract>, amount: u64) -> Result<()>
             ErrorCode::AlreadyFullyFunded
         );
 
-        // Refund each investor. In this example, we assume each investor calls
-        // a separate "refund_investor" instruction or we iterate over InvestorRecords.
+        if contract.soft_cap_met() {
+            // Buyback is quoted on amount_funded_so_far, so it scales with the raise
+            contract.status = ContractStatus::FundedPendingVerification;
+            contract.funded_time = clock.unix_timestamp;
+            return Ok(());
+        }
 
-        // We'll just set status => Cancelled here. Real code should do the actual refunds.
+        // Each investor pulls their own principal back out via `claim_refund`.
         contract.status = ContractStatus::Cancelled;
         Ok(())
     }
 
-    /// (3) Admin withdraw => contract => Active
-    /// Admin has 1 month from funded_time to do this
-    pub fn admin_withdraw(ctx: Context<AdminWithdraw>) -> Result<()> {
-        let admin_key = Pubkey::from_str(ADMIN_ADDRESS).unwrap();
-        require!(ctx.accounts.admin.key() == admin_key, ErrorCode::Unauthorized);
-
+    /// (3d) Anyone can cancel once the admin lets the verification window lapse
+    /// Escrow then becomes refundable through `claim_refund`.
+    pub fn expire_verification(ctx: Context<ExpireVerification>) -> Result<()> {
         let contract = &mut ctx.accounts.contract;
         let clock = Clock::get()?;
 
@@ -237,14 +827,30 @@ pub fn invest_contract(ctx: Context<InvestContract>, amount: u64) -> Result<()>
             contract.status == ContractStatus::FundedPendingVerification,
             ErrorCode::InvalidContractStatus
         );
+        require!(
+            clock.unix_timestamp - contract.funded_time > contract.admin_window,
+            ErrorCode::AdminWindowNotExpired
+        );
+
+        contract.status = ContractStatus::Cancelled;
+        Ok(())
+    }
+
+    /// (2c) Investor pulls their principal back out of escrow once the contract is Cancelled
+    pub fn claim_refund(ctx: Context<ClaimRefund>) -> Result<()> {
+        let contract = &ctx.accounts.contract;
+        let record = &mut ctx.accounts.investor_record;
 
-        // 1-month window for admin verification
         require!(
-            clock.unix_timestamp - contract.funded_time <= 30 * 86400,
-            ErrorCode::AdminWindowExpired
+            contract.status == ContractStatus::Cancelled,
+            ErrorCode::InvalidContractStatus
         );
+        require!(!record.refunded, ErrorCode::AlreadyRefunded);
+        require!(record.amount > 0, ErrorCode::NothingToRefund);
+        // Cancellation only happens before Active, and positions are only minted
+        // from Active on, so the original investor is always the holder here.
+        require!(!record.nft_minted, ErrorCode::NotPositionHolder);
 
-        // Transfer from escrow => admin's token account
         let seeds = &[
             b"contract",
             contract.admin.as_ref(),
@@ -257,47 +863,117 @@ pub fn invest_contract(ctx: Context<InvestContract>, amount: u64) -> Result<()>
             ctx.accounts.token_program.to_account_info(),
             Transfer {
                 from: ctx.accounts.escrow_vault.to_account_info(),
-                to: ctx.accounts.admin_token_account.to_account_info(),
+                to: ctx.accounts.investor_token_account.to_account_info(),
                 authority: contract.to_account_info(),
             },
             signer,
         );
-        token::transfer(cpi_ctx, contract.amount_funded_so_far)?;
-
-        // Mark contract => Active
-        contract.status = ContractStatus::Active;
-        contract.start_time = clock.unix_timestamp;
-
-        // Distribute NFTs to each investor (placeholder).
-        // Real code would iterate over all InvestorRecords for this contract
-        // and call a "mint_nft_to_investor" CPI to Metaplex or the token program.
-        // e.g. distribute_nfts(ctx, contract.key())?
+        token::transfer(cpi_ctx, record.amount)?;
 
+        // The amount stays on the record for bookkeeping; the flag blocks a second payout.
+        record.refunded = true;
         Ok(())
     }
 
-    /// (4) Admin cancels => refunds all invests
-    pub fn admin_cancel(ctx: Context<AdminCancel>) -> Result<()> {
-        let admin_key = Pubkey::from_str(ADMIN_ADDRESS).unwrap();
-        require!(ctx.accounts.admin.key() == admin_key, ErrorCode::Unauthorized);
-
-        let contract = &mut ctx.accounts.contract;
+    /// (3a) Treasurer opens a withdrawal proposal for the whole escrow
+    pub fn propose_withdraw(ctx: Context<ProposeWithdraw>) -> Result<()> {
+        let contract = &ctx.accounts.contract;
         let clock = Clock::get()?;
 
         require!(
             contract.status == ContractStatus::FundedPendingVerification,
             ErrorCode::InvalidContractStatus
         );
+        require!(contract.verified, ErrorCode::NotVerified);
+
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.contract = contract.key();
+        proposal.proposer = ctx.accounts.admin.key();
+        proposal.amount = contract.amount_funded_so_far;
+        proposal.approvals = Vec::new();
+        proposal.created_at = clock.unix_timestamp;
+        proposal.expires_at = clock.unix_timestamp + WITHDRAW_PROPOSAL_TTL;
+        proposal.bump = ctx.bumps.proposal;
+        Ok(())
+    }
+
+    /// (3b) A registered multisig signer approves the open proposal
+    pub fn approve_withdraw(ctx: Context<ApproveWithdraw>) -> Result<()> {
+        let signer = ctx.accounts.signer.key();
+        let proposal = &mut ctx.accounts.proposal;
+        let clock = Clock::get()?;
 
-        // 1-month window
         require!(
-            clock.unix_timestamp - contract.funded_time <= 30 * 86400,
-            ErrorCode::AdminWindowExpired
+            ctx.accounts.multisig.signers.contains(&signer),
+            ErrorCode::Unauthorized
+        );
+        require!(
+            clock.unix_timestamp <= proposal.expires_at,
+            ErrorCode::ProposalExpired
+        );
+        require!(
+            !proposal.approvals.contains(&signer),
+            ErrorCode::AlreadyApproved
         );
 
-        // Refund invests. For simplicity, we just transfer the entire escrow => the
-        // single "investor_token_account" if there's only one. For partial invests,
-        // you'd iterate. We'll just do one investor for demonstration.
+        proposal.approvals.push(signer);
+        Ok(())
+    }
+
+    /// (3c) Proposer can withdraw a proposal at any time; anyone can clear an expired one
+    pub fn cancel_withdraw_proposal(ctx: Context<CancelWithdrawProposal>) -> Result<()> {
+        let proposal = &ctx.accounts.proposal;
+        let clock = Clock::get()?;
+
+        require!(
+            ctx.accounts.caller.key() == proposal.proposer
+                || clock.unix_timestamp > proposal.expires_at,
+            ErrorCode::Unauthorized
+        );
+        Ok(())
+    }
+
+    /// (3) Admin withdraw => contract => Active
+    /// Admin has `admin_window` seconds from funded_time to do this
+    pub fn admin_withdraw(ctx: Context<AdminWithdraw>) -> Result<()> {
+        let contract = &mut ctx.accounts.contract;
+        let clock = Clock::get()?;
+        require!(!ctx.accounts.config.is_paused(contract), ErrorCode::Paused);
+
+        require!(
+            contract.status == ContractStatus::FundedPendingVerification,
+            ErrorCode::InvalidContractStatus
+        );
+        require!(contract.verified, ErrorCode::NotVerified);
+
+        // Admin verification window
+        require!(
+            clock.unix_timestamp - contract.funded_time <= contract.admin_window,
+            ErrorCode::AdminWindowExpired
+        );
+
+        // M-of-N approval, counting only signers still registered
+        let proposal = &ctx.accounts.proposal;
+        require!(
+            clock.unix_timestamp <= proposal.expires_at,
+            ErrorCode::ProposalExpired
+        );
+        require!(
+            proposal.amount == contract.amount_funded_so_far,
+            ErrorCode::ProposalMismatch
+        );
+        let multisig = &ctx.accounts.multisig;
+        let approvals = proposal
+            .approvals
+            .iter()
+            .filter(|k| multisig.signers.contains(k))
+            .count();
+        require!(
+            approvals >= multisig.threshold as usize,
+            ErrorCode::NotEnoughApprovals
+        );
+
+        // Transfer from escrow => borrower's token account
         let seeds = &[
             b"contract",
             contract.admin.as_ref(),
@@ -310,13 +986,39 @@ pub fn invest_contract(ctx: Context<InvestContract>, amount: u64) -> Result<()>
             ctx.accounts.token_program.to_account_info(),
             Transfer {
                 from: ctx.accounts.escrow_vault.to_account_info(),
-                to: ctx.accounts.investor_token_account.to_account_info(),
+                to: ctx.accounts.borrower_token_account.to_account_info(),
                 authority: contract.to_account_info(),
             },
             signer,
         );
         token::transfer(cpi_ctx, contract.amount_funded_so_far)?;
 
+        // Mark contract => Active
+        contract.status = ContractStatus::Active;
+        contract.start_time = clock.unix_timestamp;
+
+        Ok(())
+    }
+
+    /// (4) Admin cancels => contract becomes refundable
+    /// Each investor then recovers their own principal through `claim_refund`.
+    pub fn admin_cancel(ctx: Context<AdminCancel>) -> Result<()> {
+        let contract = &mut ctx.accounts.contract;
+        let clock = Clock::get()?;
+
+        require!(
+            contract.status == ContractStatus::FundedPendingVerification,
+            ErrorCode::InvalidContractStatus
+        );
+
+        // Admin verification window
+        require!(
+            clock.unix_timestamp - contract.funded_time <= contract.admin_window,
+            ErrorCode::AdminWindowExpired
+        );
+
+        // Escrow stays put; refunds are paid per InvestorRecord so nobody
+        // can be handed someone else's principal.
         contract.status = ContractStatus::Cancelled;
         Ok(())
     }
@@ -336,17 +1038,59 @@ pub fn invest_contract(ctx: Context<InvestContract>, amount: u64) -> Result<()>
 
         if clock.unix_timestamp >= end_time {
             contract.status = ContractStatus::PendingBuyback;
-            // buyback window = duration segundos
-            contract.buyback_deadline = end_time + 30 * 86400;
+            contract.buyback_deadline = end_time + contract.buyback_window;
+            contract.mark_buyback_covered(clock.unix_timestamp)?;
          }
 
         Ok(())
     }
 
+    /// (5b) Borrower repays everything before maturity => straight to settlement
+    /// Yield follows the contract's prepayment policy.
+    pub fn early_repay(ctx: Context<EarlyRepay>) -> Result<()> {
+        let contract = &mut ctx.accounts.contract;
+        let clock = Clock::get()?;
+
+        require!(
+            contract.status == ContractStatus::Active,
+            ErrorCode::InvalidContractStatus
+        );
+        require!(
+            clock.unix_timestamp < contract.start_time + contract.duration,
+            ErrorCode::ContractMatured
+        );
+        require!(!contract.has_schedule, ErrorCode::ScheduledContract);
+
+        // Freeze accrual first so the quote below follows the prepayment policy
+        contract.early_repaid_at = clock.unix_timestamp;
+        let owed = contract.calculate_buyback(clock.unix_timestamp)?;
+        let top_up = owed.saturating_sub(contract.buyback_funded);
+
+        if top_up > 0 {
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.borrower_token_account.to_account_info(),
+                    to: ctx.accounts.buyback_vault.to_account_info(),
+                    authority: ctx.accounts.borrower.to_account_info(),
+                },
+            );
+            token::transfer(cpi_ctx, top_up)?;
+            contract.buyback_funded = math::add(contract.buyback_funded, top_up)?;
+        }
+
+        contract.status = ContractStatus::PendingBuyback;
+        contract.buyback_deadline = clock.unix_timestamp + contract.buyback_window;
+        // The top-up above leaves the vault holding the whole buyback
+        contract.mark_buyback_covered(clock.unix_timestamp)?;
+        Ok(())
+    }
+
     pub fn settle_investor(ctx: Context<SettleInvestor>) -> Result<()> {
         let contract = &mut ctx.accounts.contract; 
         let record   = &mut ctx.accounts.investor_record;
         let clock    = Clock::get()?;
+        require!(!ctx.accounts.config.is_paused(contract), ErrorCode::Paused);
     
         // 1) Sólo PendingBuyback o Prolonged
         require!(
@@ -354,27 +1098,19 @@ pub fn invest_contract(ctx: Context<InvestContract>, amount: u64) -> Result<()>
             ErrorCode::InvalidContractStatus
         );
     
-        // 2) Chequea que sea el admin correcto
-        require!(
-            ctx.accounts.admin.key() == Pubkey::from_str(ADMIN_ADDRESS).unwrap(),
-            ErrorCode::Unauthorized
-        );
+        // 3) Sin chequeo de deadline: un pause puede agotar la ventana, y pagar tarde
+        //    sigue siendo posible hasta que default_contract cierre el contrato
     
-        // 3) Ventana de buyback
-        let deadline = if contract.status == ContractStatus::PendingBuyback {
-            contract.buyback_deadline
-        } else {
-            contract.prolonged_deadline
-        };
+        require!(record.amount > 0, ErrorCode::AlreadySettled);
+        let payee = record.payee(ctx.accounts.position_token_account.as_ref())?;
         require!(
-            clock.unix_timestamp <= deadline,
-            ErrorCode::SettlementWindowExpired
+            ctx.accounts.investor_token_account.owner == payee,
+            ErrorCode::NotPositionHolder
         );
-    
-        // 4) Calcula cuánto devolver
-        let principal = record.amount;
-        let yield_amt = (principal as i128 * contract.yield_percentage as i128 / 100) as u64;
-        let total     = principal.checked_add(yield_amt).ok_or(ErrorCode::InsufficientBuyback)?;
+        require!(!contract.has_schedule, ErrorCode::ScheduledContract);
+
+        // 4) Calcula cuánto devolver (yield devengado hasta hoy)
+        let total = contract.investor_payout(record.amount, clock.unix_timestamp)?;
     
         // 5) Haz el transfer SPL
         let cpi_ctx = CpiContext::new(
@@ -387,177 +1123,2229 @@ pub fn invest_contract(ctx: Context<InvestContract>, amount: u64) -> Result<()>
         );
         token::transfer(cpi_ctx, total)?;
     
-        // 6) Marca el record como pagado
+        // 6) Marca el record como pagado y congela el NFT de la posición
+        contract.record_settlement(record.amount, total, clock.unix_timestamp)?;
         record.amount = 0;
+        record.redeemed = true;
+
+        let seeds = &[
+            b"contract",
+            contract.admin.as_ref(),
+            &contract.contract_id.to_le_bytes(),
+            &[ctx.bumps.contract],
+        ];
+        freeze_position(
+            record,
+            ctx.accounts.position_token_account.as_ref(),
+            ctx.accounts.position_mint.as_ref(),
+            contract,
+            &ctx.accounts.token_program,
+            &[&seeds[..]],
+        )?;
 
         Ok(())
     }
     
-    // (9) Cerrar el contrato una vez se hayan liquidado todos los inversores
-pub fn close_contract(ctx: Context<CloseContract>) -> Result<()> {
-    let contract = &mut ctx.accounts.contract;
-    // 1) Solo el admin puede
-    require!(
-      ctx.accounts.admin.key() == Pubkey::from_str(ADMIN_ADDRESS).unwrap(),
-      ErrorCode::Unauthorized
-    );
-    // 2) Debe estar todavía en PendingBuyback o Prolonged
-    require!(
-      matches!(contract.status, ContractStatus::PendingBuyback | ContractStatus::Prolonged),
-      ErrorCode::InvalidContractStatus
-    );
-    // 3) Marcamos como Settled
-    contract.status = ContractStatus::Settled;
-    Ok(())
-}
+    /// (6b) Borrower repays into the buyback vault investors are paid from
+    pub fn fund_buyback(ctx: Context<FundBuyback>, amount: u64) -> Result<()> {
+        let contract = &mut ctx.accounts.contract;
+        let clock = Clock::get()?;
 
+        require!(
+            matches!(
+                contract.status,
+                ContractStatus::Active | ContractStatus::PendingBuyback | ContractStatus::Prolonged
+            ),
+            ErrorCode::InvalidContractStatus
+        );
+        require!(!contract.has_schedule, ErrorCode::ScheduledContract);
+        let funded = math::add(contract.buyback_funded, amount)?;
+        // Cap at what would be owed if every investor were paid as late as possible
+        let latest = contract.latest_repayment_time().max(clock.unix_timestamp);
+        require!(
+            funded <= contract.calculate_buyback(latest)?,
+            ErrorCode::ExceedsBuyback
+        );
 
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.borrower_token_account.to_account_info(),
+                to: ctx.accounts.buyback_vault.to_account_info(),
+                authority: ctx.accounts.borrower.to_account_info(),
+            },
+        );
+        token::transfer(cpi_ctx, amount)?;
 
+        contract.buyback_funded = funded;
+        contract.mark_buyback_covered(clock.unix_timestamp)?;
+        Ok(())
+    }
 
-    /// (7) Admin can request a 2-week prolongation
-    pub fn prolong_contract(ctx: Context<ProlongContract>) -> Result<()> {
+    /// (6c) Anyone can push an investor's principal + yield out of the buyback vault
+    pub fn claim_payout(ctx: Context<ClaimPayout>) -> Result<()> {
         let contract = &mut ctx.accounts.contract;
-        let _clock = Clock::get()?;
+        let record = &mut ctx.accounts.investor_record;
+        require!(!ctx.accounts.config.is_paused(contract), ErrorCode::Paused);
 
         require!(
-            contract.status == ContractStatus::PendingBuyback,
+            matches!(contract.status, ContractStatus::PendingBuyback | ContractStatus::Prolonged),
             ErrorCode::InvalidContractStatus
         );
+        require!(record.amount > 0, ErrorCode::AlreadySettled);
+        let payee = record.payee(ctx.accounts.position_token_account.as_ref())?;
+        require!(
+            ctx.accounts.investor_token_account.owner == payee,
+            ErrorCode::NotPositionHolder
+        );
+        require!(!contract.has_schedule, ErrorCode::ScheduledContract);
 
-        let admin_key = Pubkey::from_str(ADMIN_ADDRESS).unwrap();
-        require!(ctx.accounts.admin.key() == admin_key, ErrorCode::Unauthorized);
+        let clock = Clock::get()?;
+        let total = contract.investor_payout(record.amount, clock.unix_timestamp)?;
+        require!(
+            ctx.accounts.buyback_vault.amount >= total,
+            ErrorCode::InsufficientBuyback
+        );
 
-        // add 2 weeks to the buyback_deadline
-        contract.prolonged_deadline = contract.buyback_deadline + 14 * 86400;
-        contract.status = ContractStatus::Prolonged;
+        let seeds = &[
+            b"contract",
+            contract.admin.as_ref(),
+            &contract.contract_id.to_le_bytes(),
+            &[ctx.bumps.contract],
+        ];
+        let signer = &[&seeds[..]];
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.buyback_vault.to_account_info(),
+                to: ctx.accounts.investor_token_account.to_account_info(),
+                authority: contract.to_account_info(),
+            },
+            signer,
+        );
+        token::transfer(cpi_ctx, total)?;
+        freeze_position(
+            record,
+            ctx.accounts.position_token_account.as_ref(),
+            ctx.accounts.position_mint.as_ref(),
+            contract,
+            &ctx.accounts.token_program,
+            signer,
+        )?;
+
+        // Same convention as settle_investor: a zeroed record has been paid.
+        contract.record_settlement(record.amount, total, clock.unix_timestamp)?;
+        record.amount = 0;
+        record.redeemed = true;
         Ok(())
     }
 
-    /// (8) If admin/farmer fails to repay after prolongation => default
-    pub fn default_contract(ctx: Context<DefaultContract>) -> Result<()> {
+    /// (6d) Borrower pays (part of) the schedule; oldest installment is filled first
+    pub fn repay(ctx: Context<Repay>, amount: u64) -> Result<()> {
         let contract = &mut ctx.accounts.contract;
-        let clock = Clock::get()?;
+        let schedule = &mut ctx.accounts.schedule;
 
         require!(
-            contract.status == ContractStatus::Prolonged,
+            matches!(
+                contract.status,
+                ContractStatus::Active | ContractStatus::PendingBuyback | ContractStatus::Prolonged
+            ),
             ErrorCode::InvalidContractStatus
         );
+        require!(amount > 0, ErrorCode::InsufficientFunds);
         require!(
-            clock.unix_timestamp > contract.prolonged_deadline,
-            ErrorCode::SettlementWindowExpired
+            amount <= math::sub(schedule.total_scheduled, schedule.total_paid)?,
+            ErrorCode::ExceedsBuyback
         );
 
-        contract.status = ContractStatus::Defaulted;
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.borrower_token_account.to_account_info(),
+                to: ctx.accounts.buyback_vault.to_account_info(),
+                authority: ctx.accounts.borrower.to_account_info(),
+            },
+        );
+        token::transfer(cpi_ctx, amount)?;
+
+        let mut remaining = amount;
+        for installment in schedule.installments.iter_mut() {
+            if remaining == 0 {
+                break;
+            }
+            let outstanding = math::sub(installment.amount, installment.paid)?;
+            let applied = outstanding.min(remaining);
+            installment.paid = math::add(installment.paid, applied)?;
+            remaining -= applied;
+        }
+
+        schedule.total_paid = math::add(schedule.total_paid, amount)?;
+        contract.buyback_funded = math::add(contract.buyback_funded, amount)?;
         Ok(())
     }
-}
 
-// ---------------------------------------------------------------------
-// Data Structures
-// ---------------------------------------------------------------------
+    /// (6e) Anyone can push an investor's pro-rata share of installments paid so far
+    pub fn claim_installments(ctx: Context<ClaimInstallments>) -> Result<()> {
+        let contract = &mut ctx.accounts.contract;
+        let schedule = &ctx.accounts.schedule;
+        let record = &mut ctx.accounts.investor_record;
+        require!(!ctx.accounts.config.is_paused(contract), ErrorCode::Paused);
 
-#[account]
-pub struct Contract {
-    // Admin / PDAs
-    pub admin: Pubkey,
-    pub token_mint: Pubkey,
-    pub nft_mint: Pubkey,
-    pub escrow_token_account: Pubkey,
-    pub farm_image_url: String,  // NEW field
+        require!(
+            matches!(
+                contract.status,
+                ContractStatus::Active
+                    | ContractStatus::PendingBuyback
+                    | ContractStatus::Prolonged
+                    | ContractStatus::Defaulted
+            ),
+            ErrorCode::InvalidContractStatus
+        );
+        require!(record.amount > 0, ErrorCode::AlreadySettled);
+        let payee = record.payee(ctx.accounts.position_token_account.as_ref())?;
+        require!(
+            ctx.accounts.investor_token_account.owner == payee,
+            ErrorCode::NotPositionHolder
+        );
 
-    // Funding
-    pub total_investment_needed: i64,
-    pub amount_funded_so_far: u64,
-    pub yield_percentage: i64,
-    pub duration: i64,
-    pub contract_id: u64,
-    pub status: ContractStatus,
+        let entitled = math::mul_div(
+            schedule.total_paid,
+            record.amount,
+            contract.amount_funded_so_far,
+            Rounding::Down,
+        )?;
+        let payout = math::sub(entitled, record.installments_claimed)?;
+        // Once the schedule is complete a zero payout still settles the record,
+        // e.g. when the last claim already moved everything but rounding dust
+        let complete = schedule.total_paid == schedule.total_scheduled;
+        require!(payout > 0 || complete, ErrorCode::NothingToClaim);
 
-    // Timestamps
-    pub upload_date: i64,
-    pub funding_deadline: i64,
-    pub start_time: i64,
-    pub funded_time: i64,
-    pub verified: bool,
+        let seeds = &[
+            b"contract",
+            contract.admin.as_ref(),
+            &contract.contract_id.to_le_bytes(),
+            &[ctx.bumps.contract],
+        ];
+        let signer = &[&seeds[..]];
 
-    // Optional buyback windows
-    pub buyback_deadline: i64,
-    pub prolonged_deadline: i64,
+        if payout > 0 {
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.buyback_vault.to_account_info(),
+                    to: ctx.accounts.investor_token_account.to_account_info(),
+                    authority: contract.to_account_info(),
+                },
+                signer,
+            );
+            token::transfer(cpi_ctx, payout)?;
+        }
+        if complete {
+            freeze_position(
+                record,
+                ctx.accounts.position_token_account.as_ref(),
+                ctx.accounts.position_mint.as_ref(),
+                contract,
+                &ctx.accounts.token_program,
+                signer,
+            )?;
+        }
 
-    // Farm details
-    pub farm_name: String,
-    pub farm_address: String,
-}
+        record.installments_claimed = entitled;
+        contract.total_repaid = math::add(contract.total_repaid, payout)?;
 
-impl Contract {
-    pub fn calculate_buyback(&self) -> u64 {
-        let principal = self.total_investment_needed;
-        let yield_amt = (principal * self.yield_percentage) / 100;
-        (principal + yield_amt).max(0) as u64
+        // Whole schedule paid and this investor has their full share => settled
+        if complete {
+            let full_share = math::mul_div(
+                schedule.total_scheduled,
+                record.amount,
+                contract.amount_funded_so_far,
+                Rounding::Up,
+            )?;
+            contract.dust = math::add(contract.dust, math::sub(full_share, entitled)?)?;
+            contract.investors_settled += 1;
+            contract.principal_settled = math::add(contract.principal_settled, record.amount)?;
+            record.amount = 0;
+            record.redeemed = true;
+        }
+        Ok(())
     }
-}
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
-pub enum ContractStatus {
-    Created,
-    Funding,
-    FundedPendingVerification,
-    Active,
-    PendingBuyback,
-    Prolonged,
-    Settled,
-    Defaulted,
-    Cancelled,
-}
+    /// (6f) Anyone can flag installments that fell due without being paid in full
+    pub fn check_installments(ctx: Context<CheckInstallments>) -> Result<()> {
+        let contract = &ctx.accounts.contract;
+        let schedule = &mut ctx.accounts.schedule;
+        let clock = Clock::get()?;
 
-#[account]
-pub struct InvestorRecord {
-    pub contract: Pubkey,
-    pub investor: Pubkey,
-    pub amount: u64,
-    pub bump: u8,
-    pub nft_minted: bool,
-    pub nft_mint: Pubkey,
+        require!(
+            matches!(
+                contract.status,
+                ContractStatus::Active | ContractStatus::PendingBuyback | ContractStatus::Prolonged
+            ),
+            ErrorCode::InvalidContractStatus
+        );
+
+        let mut newly_missed = 0u8;
+        for (index, installment) in schedule.installments.iter_mut().enumerate() {
+            let due_time = contract.start_time + installment.due_offset;
+            if installment.missed || clock.unix_timestamp <= due_time {
+                continue;
+            }
+            if installment.paid < installment.amount {
+                installment.missed = true;
+                newly_missed += 1;
+                emit!(InstallmentMissed {
+                    contract: contract.key(),
+                    index: index as u8,
+                    due_time,
+                    outstanding: installment.amount - installment.paid,
+                });
+            }
+        }
+        schedule.missed_count += newly_missed;
+        Ok(())
+    }
+
+    // (9) Cerrar el contrato una vez se hayan liquidado todos los inversores
+pub fn close_contract(ctx: Context<CloseContract>) -> Result<()> {
+    let contract = &mut ctx.accounts.contract;
+    // 1) Solo el treasurer puede (comprobado en CloseContract)
+    // 2) Debe estar todavía en PendingBuyback o Prolonged
+    require!(
+      matches!(contract.status, ContractStatus::PendingBuyback | ContractStatus::Prolonged),
+      ErrorCode::InvalidContractStatus
+    );
+    // 3) Todos los inversores deben haber cobrado; si no, el resto va por default_contract
+    require!(
+      contract.investors_settled == contract.investor_count,
+      ErrorCode::InvestorsNotSettled
+    );
+    // 4) Marcamos como Settled
+    contract.status = ContractStatus::Settled;
+    Ok(())
 }
 
-// ---------------------------------------------------------------------
-// Contexts (same as before, but update CreateContract to include farm_image_url)
-// ---------------------------------------------------------------------
-#[derive(Accounts)]
-#[instruction(total_investment_needed: u64, yield_percentage: i64, duration_in_seconds: i64, contract_id: u64, nft_mint: Pubkey, farm_name: String, farm_address: String, farm_image_url: String)]
-pub struct CreateContract<'info> {
-    #[account(mut)]
-    pub admin: Signer<'info>,
-    pub token_mint: Account<'info, Mint>,
+    /// (6g) Borrower takes back whatever is left in the buyback vault (overfunding
+    /// and rounding dust) once every investor has been paid and the contract is Settled
+    pub fn sweep_buyback_vault(ctx: Context<SweepBuybackVault>) -> Result<()> {
+        let contract = &ctx.accounts.contract;
+        require!(
+            contract.status == ContractStatus::Settled,
+            ErrorCode::InvalidContractStatus
+        );
+        let leftover = ctx.accounts.buyback_vault.amount;
+        require!(leftover > 0, ErrorCode::NothingToClaim);
 
-    #[account(
-        init,
+        let seeds = &[
+            b"contract",
+            contract.admin.as_ref(),
+            &contract.contract_id.to_le_bytes(),
+            &[ctx.bumps.contract],
+        ];
+        let signer = &[&seeds[..]];
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.buyback_vault.to_account_info(),
+                to: ctx.accounts.borrower_token_account.to_account_info(),
+                authority: contract.to_account_info(),
+            },
+            signer,
+        );
+        token::transfer(cpi_ctx, leftover)?;
+        Ok(())
+    }
+
+
+
+
+    /// (7) Treasurer asks investors for more time: `extension_seconds` more on the
+    /// deadline, paying `sweetener_bps` of principal on top if approved
+    pub fn request_prolongation(
+        ctx: Context<RequestProlongation>,
+        extension_seconds: i64,
+        sweetener_bps: u16,
+    ) -> Result<()> {
+        let contract = &mut ctx.accounts.contract;
+        let clock = Clock::get()?;
+
+        require!(
+            matches!(contract.status, ContractStatus::PendingBuyback | ContractStatus::Prolonged),
+            ErrorCode::InvalidContractStatus
+        );
+        // claim_installments pays the schedule only, never a sweetener or late penalty
+        require!(!contract.has_schedule, ErrorCode::ScheduledContract);
+        require!(
+            contract.prolongation_count < MAX_PROLONGATIONS,
+            ErrorCode::TooManyProlongations
+        );
+        require!(
+            (MIN_WINDOW_SECONDS..=contract.prolong_extension).contains(&extension_seconds),
+            ErrorCode::InvalidWindow
+        );
+        require!(sweetener_bps <= MAX_SWEETENER_BPS, ErrorCode::InvalidRate);
+        // A sweetener would lift quotes above what the vault already holds
+        require!(contract.buyback_covered_at == 0, ErrorCode::BuybackCovered);
+
+        let deadline = contract.settlement_deadline();
+        require!(
+            clock.unix_timestamp < deadline,
+            ErrorCode::SettlementWindowExpired
+        );
+
+        let request = &mut ctx.accounts.request;
+        request.contract = contract.key();
+        request.requester = ctx.accounts.admin.key();
+        request.round = contract.prolongation_count;
+        request.nonce = contract.prolongation_requests;
+        request.extension_seconds = extension_seconds;
+        request.sweetener_bps = sweetener_bps;
+        request.votes_for = 0;
+        request.votes_against = 0;
+        // Outstanding principal when the vote opens; votes keep their weight
+        // after a record is paid, so the denominator must not shrink either
+        request.total_weight = math::sub(contract.amount_funded_so_far, contract.principal_settled)?;
+        request.voting_ends = (clock.unix_timestamp + PROLONGATION_VOTING_WINDOW).min(deadline);
+        request.bump = ctx.bumps.request;
+        contract.prolongation_open = true;
+        contract.prolongation_requests += 1;
+        Ok(())
+    }
+
+    /// (7b) Investor votes with the weight of their outstanding principal
+    pub fn vote_prolongation(ctx: Context<VoteProlongation>, approve: bool) -> Result<()> {
+        let request = &mut ctx.accounts.request;
+        let record = &ctx.accounts.investor_record;
+        let weight = record.amount;
+        let clock = Clock::get()?;
+
+        let holder = record.payee(ctx.accounts.position_token_account.as_ref())?;
+        require!(ctx.accounts.voter.key() == holder, ErrorCode::NotPositionHolder);
+
+        require!(
+            clock.unix_timestamp <= request.voting_ends,
+            ErrorCode::VotingClosed
+        );
+        require!(weight > 0, ErrorCode::AlreadySettled);
+
+        if approve {
+            request.votes_for = math::add(request.votes_for, weight)?;
+        } else {
+            request.votes_against = math::add(request.votes_against, weight)?;
+        }
+
+        let vote = &mut ctx.accounts.vote;
+        vote.request = request.key();
+        vote.investor = record.investor;
+        vote.approve = approve;
+        vote.weight = weight;
+        Ok(())
+    }
+
+    /// (7c) Anyone closes the vote once a majority of outstanding principal has decided
+    /// or the window ended; approval extends the deadline and adds the sweetener
+    pub fn finalize_prolongation(ctx: Context<FinalizeProlongation>) -> Result<()> {
+        let contract = &mut ctx.accounts.contract;
+        let request = &ctx.accounts.request;
+        let clock = Clock::get()?;
+
+        require!(
+            matches!(contract.status, ContractStatus::PendingBuyback | ContractStatus::Prolonged),
+            ErrorCode::InvalidContractStatus
+        );
+
+        // Absolute majority of the principal outstanding when the request opened
+        let total_weight = request.total_weight as u128;
+        // Once the vault covers the buyback the quotes are frozen and a sweetener
+        // couldn't be paid, so the request lapses unapproved
+        let covered = contract.buyback_covered_at != 0;
+        let approved = !covered && request.votes_for as u128 * 2 > total_weight;
+        let rejected = request.votes_against as u128 * 2 >= total_weight;
+        require!(
+            approved || rejected || covered || clock.unix_timestamp > request.voting_ends,
+            ErrorCode::VotingOpen
+        );
+
+        if approved {
+            let deadline = contract.settlement_deadline();
+            contract.prolonged_deadline = deadline + request.extension_seconds;
+            contract.extension_bonus_bps += request.sweetener_bps;
+            contract.prolongation_count += 1;
+            contract.status = ContractStatus::Prolonged;
+        }
+        contract.prolongation_open = false;
+
+        emit!(ProlongationDecided {
+            contract: contract.key(),
+            round: request.round,
+            approved,
+            extension_seconds: request.extension_seconds,
+            sweetener_bps: request.sweetener_bps,
+            votes_for: request.votes_for,
+            votes_against: request.votes_against,
+        });
+        Ok(())
+    }
+
+    /// (8) If admin/farmer fails to repay by the buyback (or prolonged) deadline
+    /// => default, once any open prolongation vote has been finalized
+    pub fn default_contract(ctx: Context<DefaultContract>) -> Result<()> {
+        let contract = &mut ctx.accounts.contract;
+        let clock = Clock::get()?;
+        // Not gated on pause: fund_buyback, repay and early_repay stay open while
+        // paused, so the deadline binds the borrower either way
+
+        require!(
+            matches!(contract.status, ContractStatus::PendingBuyback | ContractStatus::Prolonged),
+            ErrorCode::InvalidContractStatus
+        );
+        require!(
+            clock.unix_timestamp > contract.settlement_deadline(),
+            ErrorCode::SettlementWindowExpired
+        );
+        require!(!contract.prolongation_open, ErrorCode::VotingOpen);
+        require!(contract.buyback_covered_at == 0, ErrorCode::BuybackCovered);
+        if contract.has_schedule {
+            // A fully paid schedule only waits on claim_installments
+            let schedule = ctx
+                .accounts
+                .schedule
+                .as_ref()
+                .ok_or(ErrorCode::MissingSchedule)?;
+            require!(
+                schedule.total_paid < schedule.total_scheduled,
+                ErrorCode::ScheduleComplete
+            );
+        }
+
+        contract.status = ContractStatus::Defaulted;
+        // Outstanding principal at default is what recoveries are shared over
+        contract.recovery_base = math::sub(contract.amount_funded_so_far, contract.principal_settled)?;
+
+        // Waterfall step 1: an unclaimed lump-sum buyback joins the recoveries.
+        // Installments already paid stay in the buyback vault for claim_installments.
+        if contract.has_schedule || contract.buyback_funded == 0 {
+            return Ok(());
+        }
+        let buyback_vault = ctx
+            .accounts
+            .buyback_vault
+            .as_ref()
+            .ok_or(ErrorCode::MissingBuybackVault)?;
+        let leftover = buyback_vault.amount;
+        if leftover == 0 {
+            return Ok(());
+        }
+
+        let seeds = &[
+            b"contract",
+            contract.admin.as_ref(),
+            &contract.contract_id.to_le_bytes(),
+            &[ctx.bumps.contract],
+        ];
+        let signer = &[&seeds[..]];
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: buyback_vault.to_account_info(),
+                to: ctx.accounts.recovery_vault.to_account_info(),
+                authority: contract.to_account_info(),
+            },
+            signer,
+        );
+        token::transfer(cpi_ctx, leftover)?;
+
+        contract.total_recovered = leftover;
+        emit!(RecoveryDeposited {
+            contract: contract.key(),
+            depositor: contract.key(),
+            amount: leftover,
+            total_recovered: contract.total_recovered,
+        });
+        Ok(())
+    }
+
+    /// (8b) Collateral sale, insurance or late payments recovered after a default
+    pub fn deposit_recovery(ctx: Context<DepositRecovery>, amount: u64) -> Result<()> {
+        let contract = &mut ctx.accounts.contract;
+
+        require!(
+            contract.status == ContractStatus::Defaulted,
+            ErrorCode::InvalidContractStatus
+        );
+        require!(amount > 0, ErrorCode::InsufficientFunds);
+
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.depositor_token_account.to_account_info(),
+                to: ctx.accounts.recovery_vault.to_account_info(),
+                authority: ctx.accounts.depositor.to_account_info(),
+            },
+        );
+        token::transfer(cpi_ctx, amount)?;
+
+        contract.total_recovered = math::add(contract.total_recovered, amount)?;
+        emit!(RecoveryDeposited {
+            contract: contract.key(),
+            depositor: ctx.accounts.depositor.key(),
+            amount,
+            total_recovered: contract.total_recovered,
+        });
+        Ok(())
+    }
+
+    /// (8c) Anyone can push an investor's pro-rata share of everything recovered so far
+    pub fn claim_recovery(ctx: Context<ClaimRecovery>) -> Result<()> {
+        let contract = &mut ctx.accounts.contract;
+        let record = &mut ctx.accounts.investor_record;
+        require!(!ctx.accounts.config.is_paused(contract), ErrorCode::Paused);
+
+        require!(
+            contract.status == ContractStatus::Defaulted,
+            ErrorCode::InvalidContractStatus
+        );
+        require!(record.amount > 0, ErrorCode::AlreadySettled);
+        let payee = record.payee(ctx.accounts.position_token_account.as_ref())?;
+        require!(
+            ctx.accounts.investor_token_account.owner == payee,
+            ErrorCode::NotPositionHolder
+        );
+
+        let entitled = math::mul_div(
+            contract.total_recovered,
+            record.amount,
+            contract.recovery_base,
+            Rounding::Down,
+        )?;
+        let payout = math::sub(entitled, record.recovery_claimed)?;
+        require!(payout > 0, ErrorCode::NothingToClaim);
+
+        let seeds = &[
+            b"contract",
+            contract.admin.as_ref(),
+            &contract.contract_id.to_le_bytes(),
+            &[ctx.bumps.contract],
+        ];
+        let signer = &[&seeds[..]];
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.recovery_vault.to_account_info(),
+                to: ctx.accounts.investor_token_account.to_account_info(),
+                authority: contract.to_account_info(),
+            },
+            signer,
+        );
+        token::transfer(cpi_ctx, payout)?;
+
+        record.recovery_claimed = entitled;
+        contract.total_repaid = math::add(contract.total_repaid, payout)?;
+        Ok(())
+    }
+
+    /// (10) Position holder offers their NFT for `price` while the contract is Active.
+    /// The NFT stays in the seller's wallet, delegated to the listing PDA.
+    pub fn list_position(ctx: Context<ListPosition>, price: u64) -> Result<()> {
+        let contract = &ctx.accounts.contract;
+        let record = &ctx.accounts.investor_record;
+        let clock = Clock::get()?;
+        require!(!ctx.accounts.config.is_paused(contract), ErrorCode::Paused);
+
+        require!(
+            contract.status == ContractStatus::Active,
+            ErrorCode::InvalidContractStatus
+        );
+        require!(record.amount > 0, ErrorCode::AlreadySettled);
+        require!(price > 0, ErrorCode::InsufficientFunds);
+
+        let holder = record.payee(Some(&ctx.accounts.seller_position_account))?;
+        require!(
+            record.nft_minted && ctx.accounts.seller.key() == holder,
+            ErrorCode::NotPositionHolder
+        );
+
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Approve {
+                to: ctx.accounts.seller_position_account.to_account_info(),
+                delegate: ctx.accounts.listing.to_account_info(),
+                authority: ctx.accounts.seller.to_account_info(),
+            },
+        );
+        token::approve(cpi_ctx, 1)?;
+
+        let listing = &mut ctx.accounts.listing;
+        listing.contract = contract.key();
+        listing.investor_record = record.key();
+        listing.seller = holder;
+        listing.position_token_account = ctx.accounts.seller_position_account.key();
+        listing.price = price;
+        listing.created_at = clock.unix_timestamp;
+        listing.bump = ctx.bumps.listing;
+        Ok(())
+    }
+
+    /// (10b) Buyer pays the price (less the protocol fee) and receives the NFT, atomically.
+    /// `max_price` guards against the seller relisting higher before the buy lands.
+    pub fn buy_position(ctx: Context<BuyPosition>, max_price: u64) -> Result<()> {
+        let contract = &ctx.accounts.contract;
+        let listing = &ctx.accounts.listing;
+        let config = &ctx.accounts.config;
+        require!(!config.is_paused(contract), ErrorCode::Paused);
+
+        // Listings only fill while Active; after that they can just be closed
+        require!(
+            contract.status == ContractStatus::Active,
+            ErrorCode::InvalidContractStatus
+        );
+        require!(
+            ctx.accounts.investor_record.amount > 0,
+            ErrorCode::AlreadySettled
+        );
+        require!(
+            listing.is_live(listing.key(), &ctx.accounts.seller_position_account),
+            ErrorCode::ListingStale
+        );
+        require!(listing.price <= max_price, ErrorCode::PriceAboveLimit);
+
+        let fee = math::bps_of(listing.price, config.fee_bps as u64, Rounding::Down)?;
+
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.buyer_token_account.to_account_info(),
+                to: ctx.accounts.seller_token_account.to_account_info(),
+                authority: ctx.accounts.buyer.to_account_info(),
+            },
+        );
+        token::transfer(cpi_ctx, math::sub(listing.price, fee)?)?;
+
+        if fee > 0 {
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.buyer_token_account.to_account_info(),
+                    to: ctx.accounts.fee_recipient_token_account.to_account_info(),
+                    authority: ctx.accounts.buyer.to_account_info(),
+                },
+            );
+            token::transfer(cpi_ctx, fee)?;
+        }
+
+        let record_key = ctx.accounts.investor_record.key();
+        let seeds = &[b"listing", record_key.as_ref(), &[listing.bump]];
+        let signer = &[&seeds[..]];
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.seller_position_account.to_account_info(),
+                to: ctx.accounts.buyer_position_account.to_account_info(),
+                authority: listing.to_account_info(),
+            },
+            signer,
+        );
+        token::transfer(cpi_ctx, 1)?;
+
+        emit!(PositionSold {
+            contract: contract.key(),
+            investor_record: record_key,
+            seller: listing.seller,
+            buyer: ctx.accounts.buyer.key(),
+            price: listing.price,
+            fee,
+        });
+        Ok(())
+    }
+
+    /// (10c) Seller withdraws a listing; anyone can clear one that can no longer fill
+    /// (contract left Active, or the seller no longer holds the delegated NFT)
+    pub fn cancel_listing(ctx: Context<CancelListing>) -> Result<()> {
+        let listing = &ctx.accounts.listing;
+
+        require!(
+            ctx.accounts.caller.key() == listing.seller
+                || ctx.accounts.contract.status != ContractStatus::Active
+                || !listing.is_live(listing.key(), &ctx.accounts.seller_position_account),
+            ErrorCode::Unauthorized
+        );
+
+        // Only the seller can sign the revoke; a listing cleared by anyone else
+        // leaves a delegate pointing at the closed PDA, which can't sign.
+        // A position frozen at settlement can't be revoked (nor moved) anymore.
+        let position = &ctx.accounts.seller_position_account;
+        if ctx.accounts.caller.key() == listing.seller
+            && position.owner == listing.seller
+            && position.delegate == COption::Some(listing.key())
+            && !position.is_frozen()
+        {
+            token::revoke(CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Revoke {
+                    source: position.to_account_info(),
+                    authority: ctx.accounts.caller.to_account_info(),
+                },
+            ))?;
+        }
+        Ok(())
+    }
+}
+
+// ---------------------------------------------------------------------
+// Data Structures
+// ---------------------------------------------------------------------
+
+#[account]
+pub struct Contract {
+    // Admin / PDAs
+    pub admin: Pubkey,
+    pub token_mint: Pubkey,
+    pub nft_mint: Pubkey,
+    pub escrow_token_account: Pubkey,
+    pub farm_image_url: String,  // NEW field
+
+    // Funding
+    pub total_investment_needed: i64,
+    pub amount_funded_so_far: u64,
+    pub apr_bps: u16,
+    pub duration: i64,
+    pub contract_id: u64,
+    pub status: ContractStatus,
+
+    // Timestamps
+    pub upload_date: i64,
+    pub funding_deadline: i64,
+    pub start_time: i64,
+    pub funded_time: i64,
+    pub verified: bool,
+
+    // Optional buyback windows
+    pub buyback_deadline: i64,
+    pub prolonged_deadline: i64,
+
+    // Farm details
+    pub farm_name: String,
+    pub farm_address: String,
+
+    // USDC deposited so far into the buyback vault
+    pub buyback_funded: u64,
+
+    // Settlement progress
+    pub investor_count: u32,
+    pub investors_settled: u32,
+    pub total_repaid: u64,
+
+    // Timing windows (seconds), fixed at creation
+    pub funding_window: i64,
+    pub admin_window: i64,
+    pub buyback_window: i64,
+    pub prolong_extension: i64,
+
+    // Per-contract emergency stop (see Config::is_paused)
+    pub paused: bool,
+
+    // Farmer who receives the funds and owes the buyback
+    pub borrower: Pubkey,
+
+    // Rounding remainders kept by the vault (quotes round up, payouts down)
+    pub dust: u64,
+
+    // Repaid through a RepaymentSchedule instead of a lump-sum buyback
+    pub has_schedule: bool,
+
+    // Early repayment terms; early_repaid_at = 0 until early_repay runs
+    pub prepayment_policy: PrepaymentPolicy,
+    pub prepayment_penalty_bps: u16,
+    pub early_repaid_at: i64,
+
+    // Penalty interest accrued from buyback_deadline while Prolonged
+    pub late_penalty_bps_per_day: u16,
+
+    // Investor-approved extensions so far and the sweeteners they added
+    pub prolongation_count: u8,
+    pub extension_bonus_bps: u16,
+
+    // Default recovery: principal repaid before default, the principal still
+    // outstanding when it happened, and everything deposited since
+    pub principal_settled: u64,
+    pub recovery_base: u64,
+    pub total_recovered: u64,
+
+    // Soft cap: a raise closing at or above this proceeds; 0 = all-or-nothing
+    pub minimum_raise: u64,
+
+    // Investor withdrawals during funding: fee-free window after each investment,
+    // then exit_fee_bps of the amount withdrawn
+    pub withdrawal_cooloff: i64,
+    pub exit_fee_bps: u16,
+
+    // A ProlongationRequest exists and hasn't been finalized; blocks default
+    pub prolongation_open: bool,
+    // Requests opened so far; each request's nonce keys its votes
+    pub prolongation_requests: u32,
+
+    // When the buyback vault first held the whole buyback; 0 = not yet.
+    // Accrual stops here so late claimers are quoted what was deposited
+    pub buyback_covered_at: i64,
+}
+
+impl Contract {
+    /// "<farm name> #<contract id>", farm name cut to fit Metaplex's 32-byte limit.
+    pub fn position_nft_name(&self) -> String {
+        let suffix = format!(" #{}", self.contract_id);
+        let mut name = self.farm_name.clone();
+        while name.len() + suffix.len() > MAX_NFT_NAME_LEN {
+            name.pop();
+        }
+        name + &suffix
+    }
+
+    /// Last moment an investor can be paid in the current repayment phase.
+    pub fn settlement_deadline(&self) -> i64 {
+        if self.status == ContractStatus::Prolonged {
+            self.prolonged_deadline
+        } else {
+            self.buyback_deadline
+        }
+    }
+
+    /// Latest time any investor could still be repaid, assuming every remaining
+    /// prolongation is requested at full length and approved.
+    pub fn latest_repayment_time(&self) -> i64 {
+        let remaining = (MAX_PROLONGATIONS - self.prolongation_count) as i64 * self.prolong_extension;
+        match self.status {
+            ContractStatus::PendingBuyback | ContractStatus::Prolonged => {
+                self.settlement_deadline() + remaining
+            }
+            _ => self.start_time + self.duration + self.buyback_window + remaining,
+        }
+    }
+
+    /// Seconds of accrual from `start_time` to `until`, capped at the settlement
+    /// deadline once the contract has matured.
+    pub fn accrual_seconds(&self, until: i64) -> i64 {
+        let end = if self.early_repaid_at != 0 {
+            match self.prepayment_policy {
+                PrepaymentPolicy::Full => self.start_time + self.duration,
+                _ => self.early_repaid_at,
+            }
+        } else {
+            match self.status {
+                ContractStatus::PendingBuyback | ContractStatus::Prolonged => {
+                    self.repaid_until(until).min(self.settlement_deadline())
+                }
+                _ => until,
+            }
+        };
+        (end - self.start_time).max(0)
+    }
+
+    /// `until`, frozen at the moment the buyback vault covered the whole buyback.
+    fn repaid_until(&self, until: i64) -> i64 {
+        if self.buyback_covered_at != 0 {
+            until.min(self.buyback_covered_at)
+        } else {
+            until
+        }
+    }
+
+    /// Records the repayment time once a lump-sum contract's vault holds the whole
+    /// buyback: `now` after maturity, maturity itself for a deposit that landed
+    /// before it (however late check_maturity runs).
+    pub fn mark_buyback_covered(&mut self, now: i64) -> Result<()> {
+        if self.buyback_covered_at != 0 || self.has_schedule {
+            return Ok(());
+        }
+        let at = match self.status {
+            ContractStatus::Active => now.max(self.start_time + self.duration),
+            ContractStatus::PendingBuyback | ContractStatus::Prolonged => now,
+            _ => return Ok(()),
+        };
+        if self.buyback_funded >= self.calculate_buyback(at)? {
+            self.buyback_covered_at = at;
+        }
+        Ok(())
+    }
+
+    /// Penalty interest on `principal` for each second past `buyback_deadline`
+    /// the investor is still unpaid (only reachable once Prolonged).
+    pub fn late_penalty(&self, principal: u64, until: i64, rounding: Rounding) -> Result<u64> {
+        if self.status != ContractStatus::Prolonged || self.late_penalty_bps_per_day == 0 {
+            return Ok(0);
+        }
+        let late_seconds =
+            (self.repaid_until(until).min(self.prolonged_deadline) - self.buyback_deadline).max(0);
+        math::mul_div(
+            principal,
+            self.late_penalty_bps_per_day as u64 * late_seconds as u64,
+            math::BPS_DENOMINATOR * 86400,
+            rounding,
+        )
+    }
+
+    /// Prepayment penalty on `principal`, if the contract was repaid early under that policy.
+    pub fn prepayment_penalty(&self, principal: u64, rounding: Rounding) -> Result<u64> {
+        if self.early_repaid_at == 0
+            || self.prepayment_policy != PrepaymentPolicy::ProratedWithPenalty
+        {
+            return Ok(0);
+        }
+        math::bps_of(principal, self.prepayment_penalty_bps as u64, rounding)
+    }
+
+    /// Principal + yield on `principal` repaid at `until`, rounded as asked.
+    pub fn amount_due(&self, principal: u64, until: i64, rounding: Rounding) -> Result<u64> {
+        let yield_amt = math::accrue(
+            principal,
+            self.apr_bps as u64,
+            self.accrual_seconds(until),
+            rounding,
+        )?;
+        let penalty = math::add(
+            math::add(
+                self.prepayment_penalty(principal, rounding)?,
+                self.late_penalty(principal, until, rounding)?,
+            )?,
+            math::bps_of(principal, self.extension_bonus_bps as u64, rounding)?,
+        )?;
+        math::add(math::add(principal, yield_amt)?, penalty)
+    }
+
+    /// Whole-contract buyback if every investor were repaid at `until` (rounded up).
+    /// Quoted on what was actually raised, which is below the target after a soft-cap close.
+    pub fn calculate_buyback(&self, until: i64) -> Result<u64> {
+        self.amount_due(self.amount_funded_so_far, until, Rounding::Up)
+    }
+
+    /// Raise is short of the target but at or above the optional soft cap.
+    pub fn soft_cap_met(&self) -> bool {
+        self.minimum_raise > 0 && self.amount_funded_so_far >= self.minimum_raise
+    }
+
+    /// What a single investor repaid at `until` receives (rounded down).
+    pub fn investor_payout(&self, principal: u64, until: i64) -> Result<u64> {
+        self.amount_due(principal, until, Rounding::Down)
+    }
+
+    /// Books a paid investor: progress counters, total repaid and the rounding
+    /// dust the payout left behind relative to the rounded-up quote.
+    pub fn record_settlement(&mut self, principal: u64, paid: u64, until: i64) -> Result<()> {
+        let quoted = self.amount_due(principal, until, Rounding::Up)?;
+        self.investors_settled += 1;
+        self.principal_settled = math::add(self.principal_settled, principal)?;
+        self.total_repaid = math::add(self.total_repaid, paid)?;
+        self.dust = math::add(self.dust, math::sub(quoted, paid)?)?;
+        Ok(())
+    }
+}
+
+/// Contract PDA as sole verified creator, so wallets can authenticate positions.
+/// It signs the metadata CPIs, which is what lets `verified` be set up front.
+fn position_nft_creators(contract: Pubkey) -> Vec<Creator> {
+    vec![Creator {
+        address: contract,
+        verified: true,
+        share: 100,
+    }]
+}
+
+/// Program-wide settings, one per deployment (seeds = ["config"])
+#[account]
+pub struct Config {
+    pub admin: Pubkey,
+    pub pending_admin: Pubkey,
+    pub allowed_mints: Vec<Pubkey>,
+    pub fee_bps: u16,
+    pub fee_recipient: Pubkey,
+    pub paused: bool,
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Role {
+    Originator,
+    Verifier,
+    Treasurer,
+    Pauser,
+}
+
+impl Role {
+    pub fn seed(&self) -> &'static [u8] {
+        match self {
+            Role::Originator => ROLE_ORIGINATOR_SEED,
+            Role::Verifier => ROLE_VERIFIER_SEED,
+            Role::Treasurer => ROLE_TREASURER_SEED,
+            Role::Pauser => ROLE_PAUSER_SEED,
+        }
+    }
+}
+
+impl Config {
+    /// Halted either protocol-wide or for this contract alone.
+    pub fn is_paused(&self, contract: &Contract) -> bool {
+        self.paused || contract.paused
+    }
+}
+
+/// Existence of this PDA is the grant; revoking closes it
+#[account]
+pub struct RoleAssignment {
+    pub member: Pubkey,
+    pub role: Role,
+    pub bump: u8,
+}
+
+/// Signers allowed to approve escrow withdrawals (seeds = ["multisig"])
+#[account]
+pub struct Multisig {
+    pub signers: Vec<Pubkey>,
+    pub threshold: u8,
+    pub bump: u8,
+}
+
+impl Multisig {
+    /// At most MAX_MULTISIG_SIGNERS distinct signers and 1 <= threshold <= signers.
+    pub fn validate(signers: &[Pubkey], threshold: u8) -> Result<()> {
+        require!(signers.len() <= MAX_MULTISIG_SIGNERS, ErrorCode::InvalidMultisig);
+        require!(
+            threshold > 0 && threshold as usize <= signers.len(),
+            ErrorCode::InvalidMultisig
+        );
+        for (i, signer) in signers.iter().enumerate() {
+            require!(!signers[..i].contains(signer), ErrorCode::InvalidMultisig);
+        }
+        Ok(())
+    }
+}
+
+/// Pending signer-set change (seeds = ["multisig-change"]); closed on execute or cancel
+#[account]
+pub struct MultisigChange {
+    pub proposer: Pubkey,
+    pub signers: Vec<Pubkey>,
+    pub threshold: u8,
+    pub approvals: Vec<Pubkey>,
+    pub expires_at: i64,
+    pub bump: u8,
+}
+
+/// One open withdrawal per contract (seeds = ["withdraw-proposal", contract])
+#[account]
+pub struct WithdrawProposal {
+    pub contract: Pubkey,
+    pub proposer: Pubkey,
+    pub amount: u64,
+    pub approvals: Vec<Pubkey>,
+    pub created_at: i64,
+    pub expires_at: i64,
+    pub bump: u8,
+}
+
+/// Open extension vote (seeds = ["prolongation", contract]); closed on finalize
+#[account]
+pub struct ProlongationRequest {
+    pub contract: Pubkey,
+    pub round: u8,
+    pub nonce: u32,
+    pub extension_seconds: i64,
+    pub sweetener_bps: u16,
+    pub votes_for: u64,
+    pub votes_against: u64,
+    pub voting_ends: i64,
+    pub bump: u8,
+    pub total_weight: u64,
+    // Treasurer who opened it; gets the rent back on finalize
+    pub requester: Pubkey,
+}
+
+/// One per investor per request; its existence blocks a second vote
+#[account]
+pub struct ProlongationVote {
+    pub request: Pubkey,
+    pub investor: Pubkey,
+    pub approve: bool,
+    pub weight: u64,
+}
+
+/// Position offered for sale (seeds = ["listing", investor_record]); closed on
+/// sale or cancel. The seller keeps the NFT, delegated to this PDA.
+#[account]
+pub struct Listing {
+    pub contract: Pubkey,
+    pub investor_record: Pubkey,
+    pub seller: Pubkey,
+    pub position_token_account: Pubkey,
+    pub price: u64,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+impl Listing {
+    /// Seller still holds the NFT and the delegation to this listing (at `address`) stands.
+    pub fn is_live(&self, address: Pubkey, position: &TokenAccount) -> bool {
+        position.owner == self.seller
+            && position.amount == 1
+            && position.delegate == COption::Some(address)
+            && position.delegated_amount >= 1
+    }
+}
+
+/// Yield owed when the borrower repays before maturity
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PrepaymentPolicy {
+    /// Full-term yield regardless of when it is repaid
+    Full,
+    /// Yield up to the repayment date only
+    Prorated,
+    /// Prorated yield plus `prepayment_penalty_bps` of principal
+    ProratedWithPenalty,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub enum ContractStatus {
+    Created,
+    Funding,
+    FundedPendingVerification,
+    Active,
+    PendingBuyback,
+    Prolonged,
+    Settled,
+    Defaulted,
+    Cancelled,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub struct InstallmentTerms {
+    /// Seconds after `start_time`
+    pub due_offset: i64,
+    pub amount: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub struct Installment {
+    pub due_offset: i64,
+    pub amount: u64,
+    pub paid: u64,
+    pub missed: bool,
+}
+
+/// Borrower repayment plan (seeds = ["schedule", contract])
+#[account]
+pub struct RepaymentSchedule {
+    pub contract: Pubkey,
+    pub installments: Vec<Installment>,
+    pub total_scheduled: u64,
+    pub total_paid: u64,
+    pub missed_count: u8,
+    pub bump: u8,
+}
+
+#[account]
+pub struct InvestorRecord {
+    pub contract: Pubkey,
+    pub investor: Pubkey,
+    pub amount: u64,
+    pub bump: u8,
+    pub nft_minted: bool,
+    pub nft_mint: Pubkey,
+    pub refunded: bool,
+    // Cumulative installment payouts received
+    pub installments_claimed: u64,
+    // Cumulative default recoveries received
+    pub recovery_claimed: u64,
+    // Start of the current withdrawal cool-off
+    pub invested_at: i64,
+    // Fully paid out; the position NFT no longer carries a claim
+    pub redeemed: bool,
+    // Principal invested since invested_at; withdrawable fee-free until the cool-off ends
+    pub cooloff_amount: u64,
+}
+
+impl InvestorRecord {
+    /// Counts `amount` invested at `now` toward the withdrawal cool-off. Top-ups
+    /// inside an open cool-off share its window; only a new window restarts the
+    /// clock, so older principal never becomes fee-free again.
+    pub fn add_to_cooloff(&mut self, amount: u64, now: i64, cooloff: i64) -> Result<()> {
+        if now - self.invested_at > cooloff {
+            self.invested_at = now;
+            self.cooloff_amount = amount;
+        } else {
+            self.cooloff_amount = math::add(self.cooloff_amount, amount)?;
+        }
+        Ok(())
+    }
+
+    /// Exit fee on withdrawing `amount` at `now`. Principal still in its cool-off
+    /// leaves first and fee-free (using up that allowance); the rest pays `exit_fee_bps`.
+    pub fn take_withdrawal_fee(
+        &mut self,
+        amount: u64,
+        now: i64,
+        cooloff: i64,
+        exit_fee_bps: u16,
+    ) -> Result<u64> {
+        let cooling = if now - self.invested_at <= cooloff {
+            self.cooloff_amount
+        } else {
+            0
+        };
+        let fee_free = amount.min(cooling);
+        self.cooloff_amount = math::sub(cooling, fee_free)?;
+        math::bps_of(math::sub(amount, fee_free)?, exit_fee_bps as u64, Rounding::Up)
+    }
+
+    /// Wallet entitled to this position: whoever holds the NFT once it is minted,
+    /// the original investor before that. `position` is the holder's NFT account.
+    pub fn payee(&self, position: Option<&Account<TokenAccount>>) -> Result<Pubkey> {
+        if !self.nft_minted {
+            return Ok(self.investor);
+        }
+        let position = position.ok_or(ErrorCode::NotPositionHolder)?;
+        require!(
+            position.mint == self.nft_mint && position.amount == 1,
+            ErrorCode::NotPositionHolder
+        );
+        Ok(position.owner)
+    }
+}
+
+/// Freezes a redeemed position's NFT in its holder's account (the contract PDA is
+/// the freeze authority), so a spent position can't change hands as a live one.
+/// No-op for records whose NFT was never minted.
+fn freeze_position<'info>(
+    record: &InvestorRecord,
+    position: Option<&Account<'info, TokenAccount>>,
+    position_mint: Option<&Account<'info, Mint>>,
+    contract: &Account<'info, Contract>,
+    token_program: &Program<'info, Token>,
+    signer: &[&[&[u8]]],
+) -> Result<()> {
+    if !record.nft_minted {
+        return Ok(());
+    }
+    let position = position.ok_or(ErrorCode::NotPositionHolder)?;
+    let position_mint = position_mint.ok_or(ErrorCode::NotPositionHolder)?;
+    token::freeze_account(CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        FreezeAccount {
+            account: position.to_account_info(),
+            mint: position_mint.to_account_info(),
+            authority: contract.to_account_info(),
+        },
+        signer,
+    ))
+}
+
+// ---------------------------------------------------------------------
+// Contexts (same as before, but update CreateContract to include farm_image_url)
+// ---------------------------------------------------------------------
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + 300,
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, crate::program::GrasschainContractSpl>,
+
+    #[account(constraint = program_data.upgrade_authority_address == Some(authority.key()) @ ErrorCode::Unauthorized)]
+    pub program_data: Account<'info, ProgramData>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    #[account(
+        mut,
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAdmin<'info> {
+    #[account(
+        mut,
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = config.pending_admin == new_admin.key() @ ErrorCode::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+    pub new_admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(role: Role, member: Pubkey)]
+pub struct GrantRole<'info> {
+    #[account(seeds = [b"config"], bump = config.bump, has_one = admin @ ErrorCode::Unauthorized)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + 64,
+        seeds = [b"role", role.seed(), member.as_ref()],
+        bump
+    )]
+    pub role_assignment: Account<'info, RoleAssignment>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(role: Role, member: Pubkey)]
+pub struct RevokeRole<'info> {
+    #[account(seeds = [b"config"], bump = config.bump, has_one = admin @ ErrorCode::Unauthorized)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        close = admin,
+        seeds = [b"role", role.seed(), member.as_ref()],
+        bump = role_assignment.bump
+    )]
+    pub role_assignment: Account<'info, RoleAssignment>,
+}
+
+#[derive(Accounts)]
+pub struct PauseProtocol<'info> {
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    pub pauser: Signer<'info>,
+
+    #[account(
+        seeds = [b"role", ROLE_PAUSER_SEED, pauser.key().as_ref()],
+        bump = role_assignment.bump
+    )]
+    pub role_assignment: Account<'info, RoleAssignment>,
+}
+
+#[derive(Accounts)]
+pub struct PauseContract<'info> {
+    #[account(
+        mut,
+        seeds = [b"contract", contract.admin.as_ref(), &contract.contract_id.to_le_bytes()],
+        bump
+    )]
+    pub contract: Account<'info, Contract>,
+
+    pub pauser: Signer<'info>,
+
+    #[account(
+        seeds = [b"role", ROLE_PAUSER_SEED, pauser.key().as_ref()],
+        bump = role_assignment.bump
+    )]
+    pub role_assignment: Account<'info, RoleAssignment>,
+}
+
+#[derive(Accounts)]
+pub struct SetMultisig<'info> {
+    #[account(seeds = [b"config"], bump = config.bump, has_one = admin @ ErrorCode::Unauthorized)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + 400,
+        seeds = [b"multisig"],
+        bump
+    )]
+    pub multisig: Account<'info, Multisig>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeMultisigChange<'info> {
+    #[account(seeds = [b"multisig"], bump = multisig.bump)]
+    pub multisig: Account<'info, Multisig>,
+
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+
+    #[account(
+        init,
+        payer = proposer,
+        space = 8 + 720,
+        seeds = [b"multisig-change"],
+        bump
+    )]
+    pub change: Account<'info, MultisigChange>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveMultisigChange<'info> {
+    #[account(mut, seeds = [b"multisig-change"], bump = change.bump)]
+    pub change: Account<'info, MultisigChange>,
+
+    #[account(seeds = [b"multisig"], bump = multisig.bump)]
+    pub multisig: Account<'info, Multisig>,
+
+    pub signer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteMultisigChange<'info> {
+    #[account(
+        mut,
+        close = proposer,
+        seeds = [b"multisig-change"],
+        bump = change.bump,
+        has_one = proposer
+    )]
+    pub change: Account<'info, MultisigChange>,
+
+    #[account(mut, seeds = [b"multisig"], bump = multisig.bump)]
+    pub multisig: Account<'info, Multisig>,
+
+    /// CHECK: receives the change rent; bound by has_one
+    #[account(mut)]
+    pub proposer: AccountInfo<'info>,
+
+    pub caller: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelMultisigChange<'info> {
+    #[account(
+        mut,
+        close = proposer,
+        seeds = [b"multisig-change"],
+        bump = change.bump,
+        has_one = proposer
+    )]
+    pub change: Account<'info, MultisigChange>,
+
+    /// CHECK: receives the change rent; bound by has_one
+    #[account(mut)]
+    pub proposer: AccountInfo<'info>,
+
+    pub caller: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(total_investment_needed: u64, apr_bps: u16, duration_in_seconds: i64, contract_id: u64, farm_name: String, farm_address: String, farm_image_url: String)]
+pub struct CreateContract<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        seeds = [b"role", ROLE_ORIGINATOR_SEED, admin.key().as_ref()],
+        bump = role_assignment.bump
+    )]
+    pub role_assignment: Account<'info, RoleAssignment>,
+
+    /// The farmer taking the financing; must co-sign the terms
+    pub borrower: Signer<'info>,
+
+    pub token_mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + 800,
+        seeds = [b"contract", admin.key().as_ref(), &contract_id.to_le_bytes()],
+        bump
+    )]
+    pub contract: Account<'info, Contract>,
+
+    #[account(
+        init,
+        payer = admin,
+        token::mint = token_mint,
+        token::authority = contract,
+        seeds = [b"escrow-vault", contract.key().as_ref()],
+        bump
+    )]
+    pub escrow_vault: Account<'info, TokenAccount>,
+
+    // Per-contract collection NFT, stored in Contract.nft_mint
+    #[account(
+        init,
+        payer = admin,
+        mint::decimals = 0,
+        mint::authority = contract,
+        mint::freeze_authority = contract,
+        seeds = [b"collection", contract.key().as_ref()],
+        bump
+    )]
+    pub collection_mint: Box<Account<'info, Mint>>,
+
+    #[account(
+        init,
+        payer = admin,
+        associated_token::mint = collection_mint,
+        associated_token::authority = contract,
+    )]
+    pub collection_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: created by the token metadata CPI at its canonical PDA
+    #[account(
+        mut,
+        seeds = [b"metadata", token_metadata_program.key().as_ref(), collection_mint.key().as_ref()],
+        seeds::program = token_metadata_program.key(),
+        bump
+    )]
+    pub collection_metadata: AccountInfo<'info>,
+
+    /// CHECK: created by the token metadata CPI at its canonical PDA
+    #[account(
+        mut,
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            collection_mint.key().as_ref(),
+            b"edition"
+        ],
+        seeds::program = token_metadata_program.key(),
+        bump
+    )]
+    pub collection_master_edition: AccountInfo<'info>,
+
+    #[account(address = anchor_spl::token::ID)]
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    /// CHECK: The token metadata program (must equal token_metadata_program_id)
+    #[account(address = token_metadata_program_id)]
+    pub token_metadata_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct SetRepaymentSchedule<'info> {
+    #[account(
+        mut,
+        seeds = [b"contract", contract.admin.as_ref(), &contract.contract_id.to_le_bytes()],
+        bump,
+        has_one = admin @ ErrorCode::Unauthorized,
+        has_one = borrower @ ErrorCode::Unauthorized
+    )]
+    pub contract: Account<'info, Contract>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub borrower: Signer<'info>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + 720,
+        seeds = [b"schedule", contract.key().as_ref()],
+        bump
+    )]
+    pub schedule: Account<'info, RepaymentSchedule>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetWithdrawalTerms<'info> {
+    #[account(
+        mut,
+        seeds = [b"contract", contract.admin.as_ref(), &contract.contract_id.to_le_bytes()],
+        bump,
+        has_one = admin @ ErrorCode::Unauthorized,
+        has_one = borrower @ ErrorCode::Unauthorized
+    )]
+    pub contract: Account<'info, Contract>,
+
+    pub admin: Signer<'info>,
+    pub borrower: Signer<'info>,
+}
+
+/// Investors can partially invest
+#[derive(Accounts)]
+pub struct InvestContract<'info> {
+    #[account(
+        mut,
+        seeds = [b"contract", contract.admin.as_ref(), &contract.contract_id.to_le_bytes()],
+        bump
+    )]
+    pub contract: Account<'info, Contract>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub investor: Signer<'info>,
+    pub token_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow-vault", contract.key().as_ref()],
+        bump
+    )]
+    pub escrow_vault: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub investor_token_account: Account<'info, TokenAccount>,
+
+    // A new or existing InvestorRecord for this (contract, investor)
+    #[account(
+        init_if_needed,
+        payer = investor,
+        space = 8 + 200,
+        seeds = [
+            b"investor-record",
+            contract.key().as_ref(),
+            investor.key().as_ref()
+        ],
+        bump
+    )]
+    pub investor_record: Account<'info, InvestorRecord>,
+
+    #[account(address = anchor_spl::token::ID)]
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimNft<'info> {
+    #[account(mut)]
+    pub investor: Signer<'info>,
+    
+    #[account(
+        seeds = [b"contract", contract.admin.as_ref(), &contract.contract_id.to_le_bytes()],
+        bump
+    )]
+    pub contract: Account<'info, Contract>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    
+    #[account(
+        mut,
+        seeds = [
+            b"investor-record",
+            contract.key().as_ref(),
+            investor.key().as_ref()
+        ],
+        bump = investor_record.bump,
+        has_one = contract,
+        has_one = investor
+    )]
+    pub investor_record: Account<'info, InvestorRecord>,
+    
+    // One position mint per record, controlled by the contract PDA
+    #[account(
+        init,
+        payer = investor,
+        mint::decimals = 0,
+        mint::authority = contract,
+        mint::freeze_authority = contract,
+        seeds = [b"position-mint", investor_record.key().as_ref()],
+        bump
+    )]
+    pub mint: Account<'info, Mint>,
+    
+    #[account(
+        init,
+        payer = investor,
+        associated_token::mint = mint,
+        associated_token::authority = investor,
+    )]
+    pub associated_token_account: Account<'info, TokenAccount>,
+    
+    /// CHECK: created by the token metadata CPI at its canonical PDA
+    #[account(
+        mut,
+        seeds = [b"metadata", token_metadata_program.key().as_ref(), mint.key().as_ref()],
+        seeds::program = token_metadata_program.key(),
+        bump
+    )]
+    pub metadata_account: AccountInfo<'info>,
+    
+    /// CHECK: created by the token metadata CPI at its canonical PDA
+    #[account(
+        mut,
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            mint.key().as_ref(),
+            b"edition"
+        ],
+        seeds::program = token_metadata_program.key(),
+        bump
+    )]
+    pub master_edition_account: AccountInfo<'info>,
+
+    #[account(address = contract.nft_mint)]
+    pub collection_mint: Box<Account<'info, Mint>>,
+
+    /// CHECK: collection metadata; size is bumped by the verify CPI
+    #[account(
+        mut,
+        seeds = [b"metadata", token_metadata_program.key().as_ref(), collection_mint.key().as_ref()],
+        seeds::program = token_metadata_program.key(),
+        bump
+    )]
+    pub collection_metadata: AccountInfo<'info>,
+
+    /// CHECK: collection master edition, checked by seeds
+    #[account(
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            collection_mint.key().as_ref(),
+            b"edition"
+        ],
+        seeds::program = token_metadata_program.key(),
+        bump
+    )]
+    pub collection_master_edition: AccountInfo<'info>,
+    
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    
+    /// CHECK: The token metadata program (must equal token_metadata_program_id)
+    #[account(address = token_metadata_program_id)]
+    pub token_metadata_program: AccountInfo<'info>,
+    
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct VerifyFunding<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"contract",
+            contract.admin.as_ref(),
+            &contract.contract_id.to_le_bytes()
+        ],
+        bump
+    )]
+    pub contract: Account<'info, Contract>,
+
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"role", ROLE_VERIFIER_SEED, admin.key().as_ref()],
+        bump = role_assignment.bump
+    )]
+    pub role_assignment: Account<'info, RoleAssignment>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Optionally expire the contract if not fully funded by the deadline
+#[derive(Accounts)]
+pub struct ExpireFunding<'info> {
+    #[account(
+        mut,
+        seeds = [b"contract", contract.admin.as_ref(), &contract.contract_id.to_le_bytes()],
+        bump
+    )]
+    pub contract: Account<'info, Contract>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Permissionless cancel after the admin verification window lapses
+#[derive(Accounts)]
+pub struct ExpireVerification<'info> {
+    #[account(
+        mut,
+        seeds = [b"contract", contract.admin.as_ref(), &contract.contract_id.to_le_bytes()],
+        bump
+    )]
+    pub contract: Account<'info, Contract>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Investor refund out of escrow after cancellation
+#[derive(Accounts)]
+pub struct ClaimRefund<'info> {
+    #[account(
+        seeds = [b"contract", contract.admin.as_ref(), &contract.contract_id.to_le_bytes()],
+        bump
+    )]
+    pub contract: Account<'info, Contract>,
+
+    pub investor: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"investor-record",
+            contract.key().as_ref(),
+            investor.key().as_ref()
+        ],
+        bump = investor_record.bump,
+        has_one = contract,
+        has_one = investor
+    )]
+    pub investor_record: Account<'info, InvestorRecord>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow-vault", contract.key().as_ref()],
+        bump
+    )]
+    pub escrow_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = contract.token_mint,
+        token::authority = investor
+    )]
+    pub investor_token_account: Account<'info, TokenAccount>,
+
+    #[account(address = anchor_spl::token::ID)]
+    pub token_program: Program<'info, Token>,
+}
+
+/// Investor exit from escrow while the raise is open
+#[derive(Accounts)]
+pub struct WithdrawInvestment<'info> {
+    #[account(
+        mut,
+        seeds = [b"contract", contract.admin.as_ref(), &contract.contract_id.to_le_bytes()],
+        bump
+    )]
+    pub contract: Account<'info, Contract>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    pub investor: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"investor-record",
+            contract.key().as_ref(),
+            investor.key().as_ref()
+        ],
+        bump = investor_record.bump,
+        has_one = contract,
+        has_one = investor
+    )]
+    pub investor_record: Account<'info, InvestorRecord>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow-vault", contract.key().as_ref()],
+        bump
+    )]
+    pub escrow_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = contract.token_mint,
+        token::authority = investor
+    )]
+    pub investor_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = contract.token_mint,
+        token::authority = config.fee_recipient
+    )]
+    pub fee_recipient_token_account: Account<'info, TokenAccount>,
+
+    #[account(address = anchor_spl::token::ID)]
+    pub token_program: Program<'info, Token>,
+}
+
+/// Admin withdraw => Active
+#[derive(Accounts)]
+pub struct AdminWithdraw<'info> {
+    #[account(
+        mut,
+        seeds = [b"contract", contract.admin.as_ref(), &contract.contract_id.to_le_bytes()],
+        bump
+    )]
+    pub contract: Account<'info, Contract>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [b"role", ROLE_TREASURER_SEED, admin.key().as_ref()],
+        bump = role_assignment.bump
+    )]
+    pub role_assignment: Account<'info, RoleAssignment>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow-vault", contract.key().as_ref()],
+        bump
+    )]
+    pub escrow_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = contract.token_mint,
+        token::authority = contract.borrower
+    )]
+    pub borrower_token_account: Account<'info, TokenAccount>,
+
+    #[account(seeds = [b"multisig"], bump = multisig.bump)]
+    pub multisig: Account<'info, Multisig>,
+
+    // Consumed on execution
+    #[account(
+        mut,
+        close = admin,
+        seeds = [b"withdraw-proposal", contract.key().as_ref()],
+        bump = proposal.bump,
+        has_one = contract
+    )]
+    pub proposal: Account<'info, WithdrawProposal>,
+
+    #[account(address = anchor_spl::token::ID)]
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Treasurer proposes withdrawing the escrow
+#[derive(Accounts)]
+pub struct ProposeWithdraw<'info> {
+    #[account(
+        seeds = [b"contract", contract.admin.as_ref(), &contract.contract_id.to_le_bytes()],
+        bump
+    )]
+    pub contract: Account<'info, Contract>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [b"role", ROLE_TREASURER_SEED, admin.key().as_ref()],
+        bump = role_assignment.bump
+    )]
+    pub role_assignment: Account<'info, RoleAssignment>,
+
+    #[account(
+        init,
         payer = admin,
-        space = 8 + 500,
-        seeds = [b"contract", admin.key().as_ref(), &contract_id.to_le_bytes()],
+        space = 8 + 450,
+        seeds = [b"withdraw-proposal", contract.key().as_ref()],
+        bump
+    )]
+    pub proposal: Account<'info, WithdrawProposal>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveWithdraw<'info> {
+    #[account(mut)]
+    pub proposal: Account<'info, WithdrawProposal>,
+
+    #[account(seeds = [b"multisig"], bump = multisig.bump)]
+    pub multisig: Account<'info, Multisig>,
+
+    pub signer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelWithdrawProposal<'info> {
+    #[account(mut, close = proposer, has_one = proposer)]
+    pub proposal: Account<'info, WithdrawProposal>,
+
+    /// CHECK: receives the proposal rent; bound by has_one
+    #[account(mut)]
+    pub proposer: AccountInfo<'info>,
+
+    pub caller: Signer<'info>,
+}
+
+/// Admin cancels => investors claim refunds
+#[derive(Accounts)]
+pub struct AdminCancel<'info> {
+    #[account(
+        mut,
+        seeds = [b"contract", contract.admin.as_ref(), &contract.contract_id.to_le_bytes()],
+        bump
+    )]
+    pub contract: Account<'info, Contract>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [b"role", ROLE_VERIFIER_SEED, admin.key().as_ref()],
+        bump = role_assignment.bump
+    )]
+    pub role_assignment: Account<'info, RoleAssignment>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Move from Active => PendingBuyback if matured
+#[derive(Accounts)]
+pub struct CheckMaturity<'info> {
+    #[account(
+        mut,
+        seeds = [b"contract", contract.admin.as_ref(), &contract.contract_id.to_le_bytes()],
+        bump
+    )]
+    pub contract: Account<'info, Contract>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Borrower prepays the whole buyback into the vault
+#[derive(Accounts)]
+pub struct EarlyRepay<'info> {
+    #[account(
+        mut,
+        seeds = [b"contract", contract.admin.as_ref(), &contract.contract_id.to_le_bytes()],
+        bump,
+        has_one = borrower @ ErrorCode::Unauthorized
+    )]
+    pub contract: Account<'info, Contract>,
+
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+
+    #[account(address = contract.token_mint)]
+    pub token_mint: Account<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = borrower,
+        token::mint = token_mint,
+        token::authority = contract,
+        seeds = [b"buyback-vault", contract.key().as_ref()],
+        bump
+    )]
+    pub buyback_vault: Account<'info, TokenAccount>,
+
+    #[account(mut, token::mint = contract.token_mint)]
+    pub borrower_token_account: Account<'info, TokenAccount>,
+
+    #[account(address = anchor_spl::token::ID)]
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SettleInvestor<'info> {
+    /// El contrato en sí (PDA)
+    #[account(
+        mut,
+        seeds = [b"contract", contract.admin.as_ref(), &contract.contract_id.to_le_bytes()],
         bump
     )]
     pub contract: Account<'info, Contract>,
 
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    /// El admin que firma (se comprueba en tiempo de ejecución)
+    #[account(mut)]
+    pub admin: Signer<'info>,
     #[account(
-        init,
-        payer = admin,
+        seeds = [b"role", ROLE_TREASURER_SEED, admin.key().as_ref()],
+        bump = role_assignment.bump
+    )]
+    pub role_assignment: Account<'info, RoleAssignment>,
+
+    /// El record del inversor a liquidar (PDA)
+    #[account(
+        mut,
+        seeds = [
+            b"investor-record",
+            contract.key().as_ref(),
+            investor.key().as_ref()
+        ],
+        bump
+    )]
+    pub investor_record: Account<'info, InvestorRecord>,
+
+    /// CHECK: El inversor dueño del record
+    pub investor: AccountInfo<'info>,
+
+    /// La cuenta USDC del admin (source)
+    #[account(mut)]
+    pub admin_token_account: Account<'info, TokenAccount>,
+
+    /// Cuenta del NFT de la posición; obligatoria si ya se minteó (se congela al liquidar)
+    #[account(mut)]
+    pub position_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// Mint del NFT de la posición; obligatorio si ya se minteó
+    #[account(address = investor_record.nft_mint)]
+    pub position_mint: Option<Account<'info, Mint>>,
+
+    /// La cuenta USDC del titular de la posición (destino)
+    #[account(mut, token::mint = contract.token_mint)]
+    pub investor_token_account: Account<'info, TokenAccount>,
+
+    /// El programa SPL Token
+    #[account(address = anchor_spl::token::ID)]
+    pub token_program: Program<'info, Token>,
+}
+
+/// Borrower repayment into the per-contract buyback vault
+#[derive(Accounts)]
+pub struct FundBuyback<'info> {
+    #[account(
+        mut,
+        seeds = [b"contract", contract.admin.as_ref(), &contract.contract_id.to_le_bytes()],
+        bump,
+        has_one = borrower @ ErrorCode::Unauthorized
+    )]
+    pub contract: Account<'info, Contract>,
+
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+
+    #[account(address = contract.token_mint)]
+    pub token_mint: Account<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = borrower,
         token::mint = token_mint,
         token::authority = contract,
-        seeds = [b"escrow-vault", contract.key().as_ref()],
+        seeds = [b"buyback-vault", contract.key().as_ref()],
         bump
     )]
-    pub escrow_vault: Account<'info, TokenAccount>,
+    pub buyback_vault: Account<'info, TokenAccount>,
+
+    #[account(mut, token::mint = contract.token_mint)]
+    pub borrower_token_account: Account<'info, TokenAccount>,
 
     #[account(address = anchor_spl::token::ID)]
     pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
 }
 
-/// Investors can partially invest
+/// Permissionless investor payout from the buyback vault
 #[derive(Accounts)]
-pub struct InvestContract<'info> {
+pub struct ClaimPayout<'info> {
     #[account(
         mut,
         seeds = [b"contract", contract.admin.as_ref(), &contract.contract_id.to_le_bytes()],
@@ -565,128 +3353,325 @@ pub struct InvestContract<'info> {
     )]
     pub contract: Account<'info, Contract>,
 
-    #[account(mut)]
-    pub investor: Signer<'info>,
-    pub token_mint: Account<'info, Mint>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
 
     #[account(
         mut,
-        seeds = [b"escrow-vault", contract.key().as_ref()],
+        seeds = [
+            b"investor-record",
+            contract.key().as_ref(),
+            investor.key().as_ref()
+        ],
+        bump = investor_record.bump,
+        has_one = contract,
+        has_one = investor
+    )]
+    pub investor_record: Account<'info, InvestorRecord>,
+
+    /// CHECK: owner of the record; only used to derive its PDA and check the payout ATA
+    pub investor: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"buyback-vault", contract.key().as_ref()],
         bump
     )]
-    pub escrow_vault: Account<'info, TokenAccount>,
+    pub buyback_vault: Account<'info, TokenAccount>,
 
+    /// Position NFT account; required once the NFT is minted (frozen on settlement)
     #[account(mut)]
+    pub position_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// Position NFT mint; required once the NFT is minted
+    #[account(address = investor_record.nft_mint)]
+    pub position_mint: Option<Account<'info, Mint>>,
+
+    // Owner checked against InvestorRecord::payee
+    #[account(mut, token::mint = contract.token_mint)]
     pub investor_token_account: Account<'info, TokenAccount>,
 
-    // A new or existing InvestorRecord for this (contract, investor)
+    #[account(address = anchor_spl::token::ID)]
+    pub token_program: Program<'info, Token>,
+}
+
+/// Borrower installment payment into the buyback vault
+#[derive(Accounts)]
+pub struct Repay<'info> {
+    #[account(
+        mut,
+        seeds = [b"contract", contract.admin.as_ref(), &contract.contract_id.to_le_bytes()],
+        bump,
+        has_one = borrower @ ErrorCode::Unauthorized
+    )]
+    pub contract: Account<'info, Contract>,
+
+    #[account(
+        mut,
+        seeds = [b"schedule", contract.key().as_ref()],
+        bump = schedule.bump,
+        has_one = contract
+    )]
+    pub schedule: Account<'info, RepaymentSchedule>,
+
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+
+    #[account(address = contract.token_mint)]
+    pub token_mint: Account<'info, Mint>,
+
     #[account(
         init_if_needed,
-        payer = investor,
-        space = 8 + 200,
+        payer = borrower,
+        token::mint = token_mint,
+        token::authority = contract,
+        seeds = [b"buyback-vault", contract.key().as_ref()],
+        bump
+    )]
+    pub buyback_vault: Account<'info, TokenAccount>,
+
+    #[account(mut, token::mint = contract.token_mint)]
+    pub borrower_token_account: Account<'info, TokenAccount>,
+
+    #[account(address = anchor_spl::token::ID)]
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Permissionless pro-rata installment payout
+#[derive(Accounts)]
+pub struct ClaimInstallments<'info> {
+    #[account(
+        mut,
+        seeds = [b"contract", contract.admin.as_ref(), &contract.contract_id.to_le_bytes()],
+        bump
+    )]
+    pub contract: Account<'info, Contract>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        seeds = [b"schedule", contract.key().as_ref()],
+        bump = schedule.bump,
+        has_one = contract
+    )]
+    pub schedule: Account<'info, RepaymentSchedule>,
+
+    #[account(
+        mut,
         seeds = [
             b"investor-record",
             contract.key().as_ref(),
             investor.key().as_ref()
         ],
-        bump
+        bump = investor_record.bump,
+        has_one = contract,
+        has_one = investor
     )]
     pub investor_record: Account<'info, InvestorRecord>,
 
+    /// CHECK: owner of the record; only used to derive its PDA and check the payout ATA
+    pub investor: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"buyback-vault", contract.key().as_ref()],
+        bump
+    )]
+    pub buyback_vault: Account<'info, TokenAccount>,
+
+    /// Position NFT account; required once the NFT is minted (frozen on settlement)
+    #[account(mut)]
+    pub position_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// Position NFT mint; required once the NFT is minted
+    #[account(address = investor_record.nft_mint)]
+    pub position_mint: Option<Account<'info, Mint>>,
+
+    // Owner checked against InvestorRecord::payee
+    #[account(mut, token::mint = contract.token_mint)]
+    pub investor_token_account: Account<'info, TokenAccount>,
+
     #[account(address = anchor_spl::token::ID)]
     pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct ClaimNft<'info> {
-    #[account(mut)]
-    pub investor: Signer<'info>,
-    
-    #[account(mut)]
+pub struct CheckInstallments<'info> {
+    #[account(
+        seeds = [b"contract", contract.admin.as_ref(), &contract.contract_id.to_le_bytes()],
+        bump
+    )]
     pub contract: Account<'info, Contract>,
-    
-    // Ensure the investor_record belongs to this investor.
-    #[account(mut, has_one = investor)]
-    pub investor_record: Account<'info, InvestorRecord>,
-    
-    // NEW: The NFT mint account is created on claim.
+
     #[account(
-        init,
-        payer = investor,
-        mint::decimals = 0,
-        mint::authority = investor,
-        mint::freeze_authority = investor,
+        mut,
+        seeds = [b"schedule", contract.key().as_ref()],
+        bump = schedule.bump,
+        has_one = contract
     )]
-    pub mint: Account<'info, Mint>,
-    
-    // NEW: The investor's associated token account for that mint.
+    pub schedule: Account<'info, RepaymentSchedule>,
+}
+
+#[derive(Accounts)]
+pub struct CloseContract<'info> {
     #[account(
-        init,
-        payer = investor,
-        associated_token::mint = mint,
-        associated_token::authority = investor,
+        mut,
+        seeds = [b"contract", contract.admin.as_ref(), &contract.contract_id.to_le_bytes()],
+        bump
     )]
-    pub associated_token_account: Account<'info, TokenAccount>,
-    
-    // NEW: Metadata account PDA – derived off-chain using the seeds:
-    // ["metadata", token_metadata_program_id.as_ref(), mint.key().as_ref()]
-    /// CHECK: This account is created by the token metadata CPI.
-    #[account(mut)]
-    pub metadata_account: AccountInfo<'info>,
-    
-    // NEW: Master edition account PDA – derived with an extra "edition" seed.
-    /// CHECK: This account is created by the token metadata CPI.
-    #[account(mut)]
-    pub master_edition_account: AccountInfo<'info>,
-    
+    pub contract: Account<'info, Contract>,
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [b"role", ROLE_TREASURER_SEED, admin.key().as_ref()],
+        bump = role_assignment.bump
+    )]
+    pub role_assignment: Account<'info, RoleAssignment>,
+}
+
+/// Borrower recovers the buyback vault's leftover after settlement
+#[derive(Accounts)]
+pub struct SweepBuybackVault<'info> {
+    #[account(
+        seeds = [b"contract", contract.admin.as_ref(), &contract.contract_id.to_le_bytes()],
+        bump,
+        has_one = borrower @ ErrorCode::Unauthorized
+    )]
+    pub contract: Account<'info, Contract>,
+
+    pub borrower: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"buyback-vault", contract.key().as_ref()],
+        bump
+    )]
+    pub buyback_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = contract.token_mint,
+        token::authority = borrower
+    )]
+    pub borrower_token_account: Account<'info, TokenAccount>,
+
+    #[account(address = anchor_spl::token::ID)]
     pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
-    
-    /// CHECK: The token metadata program (must equal token_metadata_program_id)
-    #[account(address = token_metadata_program_id)]
-    pub token_metadata_program: AccountInfo<'info>,
-    
+}
+
+
+/// Treasurer opens a prolongation vote
+#[derive(Accounts)]
+pub struct RequestProlongation<'info> {
+    #[account(
+        mut,
+        seeds = [b"contract", contract.admin.as_ref(), &contract.contract_id.to_le_bytes()],
+        bump
+    )]
+    pub contract: Account<'info, Contract>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [b"role", ROLE_TREASURER_SEED, admin.key().as_ref()],
+        bump = role_assignment.bump
+    )]
+    pub role_assignment: Account<'info, RoleAssignment>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + 140,
+        seeds = [b"prolongation", contract.key().as_ref()],
+        bump
+    )]
+    pub request: Account<'info, ProlongationRequest>,
+
     pub system_program: Program<'info, System>,
-    pub rent: Sysvar<'info, Rent>,
 }
 
-#[derive(Accounts)]
-pub struct VerifyFunding<'info> {
+#[derive(Accounts)]
+pub struct VoteProlongation<'info> {
+    #[account(
+        seeds = [b"contract", contract.admin.as_ref(), &contract.contract_id.to_le_bytes()],
+        bump
+    )]
+    pub contract: Account<'info, Contract>,
+
+    #[account(
+        mut,
+        seeds = [b"prolongation", contract.key().as_ref()],
+        bump = request.bump,
+        has_one = contract
+    )]
+    pub request: Account<'info, ProlongationRequest>,
+
+    /// Original investor, or the position NFT holder once minted
+    #[account(mut)]
+    pub voter: Signer<'info>,
+
+    #[account(
+        seeds = [
+            b"investor-record",
+            contract.key().as_ref(),
+            investor_record.investor.as_ref()
+        ],
+        bump = investor_record.bump,
+        has_one = contract
+    )]
+    pub investor_record: Account<'info, InvestorRecord>,
+
+    pub position_token_account: Option<Account<'info, TokenAccount>>,
+
+    // Keyed by record, not voter, so a sold position can't vote twice
     #[account(
-        mut,
+        init,
+        payer = voter,
+        space = 8 + 80,
         seeds = [
-            b"contract",
-            contract.admin.as_ref(),
-            &contract.contract_id.to_le_bytes()
+            b"prolongation-vote",
+            contract.key().as_ref(),
+            investor_record.investor.as_ref(),
+            &request.nonce.to_le_bytes()
         ],
         bump
     )]
-    pub contract: Account<'info, Contract>,
-
-    /// CHECK: must match your ADMIN_ADDRESS constant
-    #[account(signer, 
-              address = Pubkey::from_str(ADMIN_ADDRESS).unwrap())]
-    pub admin: AccountInfo<'info>,
+    pub vote: Account<'info, ProlongationVote>,
 
     pub system_program: Program<'info, System>,
 }
 
-/// Optionally expire the contract if not fully funded by the deadline
 #[derive(Accounts)]
-pub struct ExpireFunding<'info> {
+pub struct FinalizeProlongation<'info> {
     #[account(
         mut,
         seeds = [b"contract", contract.admin.as_ref(), &contract.contract_id.to_le_bytes()],
         bump
     )]
     pub contract: Account<'info, Contract>,
-    pub system_program: Program<'info, System>,
+
+    // Rent goes back to whoever opened the request
+    #[account(
+        mut,
+        close = requester,
+        seeds = [b"prolongation", contract.key().as_ref()],
+        bump = request.bump,
+        has_one = contract,
+        has_one = requester
+    )]
+    pub request: Account<'info, ProlongationRequest>,
+
+    /// CHECK: receives the request rent; bound by has_one
+    #[account(mut)]
+    pub requester: AccountInfo<'info>,
+
+    pub payer: Signer<'info>,
 }
 
-/// Admin withdraw => Active
+/// Default if not repaid after prolongation; opens the recovery vault
 #[derive(Accounts)]
-pub struct AdminWithdraw<'info> {
+pub struct DefaultContract<'info> {
     #[account(
         mut,
         seeds = [b"contract", contract.admin.as_ref(), &contract.contract_id.to_le_bytes()],
@@ -695,142 +3680,317 @@ pub struct AdminWithdraw<'info> {
     pub contract: Account<'info, Contract>,
 
     #[account(mut)]
-    pub admin: Signer<'info>,
+    pub payer: Signer<'info>,
+
+    #[account(address = contract.token_mint)]
+    pub token_mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = payer,
+        token::mint = token_mint,
+        token::authority = contract,
+        seeds = [b"recovery-vault", contract.key().as_ref()],
+        bump
+    )]
+    pub recovery_vault: Account<'info, TokenAccount>,
 
+    /// Required when a lump-sum buyback was (partly) funded
     #[account(
         mut,
-        seeds = [b"escrow-vault", contract.key().as_ref()],
+        seeds = [b"buyback-vault", contract.key().as_ref()],
         bump
     )]
-    pub escrow_vault: Account<'info, TokenAccount>,
+    pub buyback_vault: Option<Account<'info, TokenAccount>>,
 
-    #[account(mut)]
-    pub admin_token_account: Account<'info, TokenAccount>,
+    /// Required when the contract repays through a schedule
+    #[account(
+        seeds = [b"schedule", contract.key().as_ref()],
+        bump = schedule.bump,
+        has_one = contract
+    )]
+    pub schedule: Option<Account<'info, RepaymentSchedule>>,
 
     #[account(address = anchor_spl::token::ID)]
     pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
 }
 
-/// Admin cancels => refunds invests
+/// Anyone paying recovered funds in for a defaulted contract
 #[derive(Accounts)]
-pub struct AdminCancel<'info> {
+pub struct DepositRecovery<'info> {
     #[account(
         mut,
         seeds = [b"contract", contract.admin.as_ref(), &contract.contract_id.to_le_bytes()],
         bump
     )]
     pub contract: Account<'info, Contract>,
-    #[account(mut)]
-    pub admin: Signer<'info>,
+
+    pub depositor: Signer<'info>,
+
+    #[account(mut, token::mint = contract.token_mint)]
+    pub depositor_token_account: Account<'info, TokenAccount>,
 
     #[account(
         mut,
-        seeds = [b"escrow-vault", contract.key().as_ref()],
+        seeds = [b"recovery-vault", contract.key().as_ref()],
         bump
     )]
-    pub escrow_vault: Account<'info, TokenAccount>,
-
-    // If single investor, or if you want to do partial refunds, you'd do more logic
-    #[account(mut)]
-    pub investor_token_account: Account<'info, TokenAccount>,
+    pub recovery_vault: Account<'info, TokenAccount>,
 
     #[account(address = anchor_spl::token::ID)]
     pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
 }
 
-/// Move from Active => PendingBuyback if matured
+/// Permissionless pro-rata recovery payout
 #[derive(Accounts)]
-pub struct CheckMaturity<'info> {
+pub struct ClaimRecovery<'info> {
     #[account(
         mut,
         seeds = [b"contract", contract.admin.as_ref(), &contract.contract_id.to_le_bytes()],
         bump
     )]
     pub contract: Account<'info, Contract>,
-    pub system_program: Program<'info, System>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"investor-record",
+            contract.key().as_ref(),
+            investor.key().as_ref()
+        ],
+        bump = investor_record.bump,
+        has_one = contract,
+        has_one = investor
+    )]
+    pub investor_record: Account<'info, InvestorRecord>,
+
+    /// CHECK: owner of the record; only used to derive its PDA and check the payout ATA
+    pub investor: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"recovery-vault", contract.key().as_ref()],
+        bump
+    )]
+    pub recovery_vault: Account<'info, TokenAccount>,
+
+    /// Position NFT account; required once the NFT is minted
+    pub position_token_account: Option<Account<'info, TokenAccount>>,
+
+    // Owner checked against InvestorRecord::payee
+    #[account(mut, token::mint = contract.token_mint)]
+    pub investor_token_account: Account<'info, TokenAccount>,
+
+    #[account(address = anchor_spl::token::ID)]
+    pub token_program: Program<'info, Token>,
 }
 
+/// Position holder lists their NFT
 #[derive(Accounts)]
-pub struct SettleInvestor<'info> {
-    /// El contrato en sí (PDA)
+pub struct ListPosition<'info> {
     #[account(
-        mut,
         seeds = [b"contract", contract.admin.as_ref(), &contract.contract_id.to_le_bytes()],
         bump
     )]
     pub contract: Account<'info, Contract>,
 
-    /// El admin que firma (se comprueba en tiempo de ejecución)
-    #[account(mut)]
-    pub admin: Signer<'info>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
 
-    /// El record del inversor a liquidar (PDA)
     #[account(
-        mut,
         seeds = [
             b"investor-record",
             contract.key().as_ref(),
-            investor.key().as_ref()
+            investor_record.investor.as_ref()
         ],
-        bump
+        bump = investor_record.bump,
+        has_one = contract
     )]
     pub investor_record: Account<'info, InvestorRecord>,
 
-    /// CHECK: El inversor dueño del record
-    pub investor: AccountInfo<'info>,
-
-    /// La cuenta USDC del admin (source)
     #[account(mut)]
-    pub admin_token_account: Account<'info, TokenAccount>,
+    pub seller: Signer<'info>,
 
-    /// La cuenta USDC del inversor (destino)
     #[account(mut)]
-    pub investor_token_account: Account<'info, TokenAccount>,
+    pub seller_position_account: Account<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = seller,
+        space = 8 + 160,
+        seeds = [b"listing", investor_record.key().as_ref()],
+        bump
+    )]
+    pub listing: Account<'info, Listing>,
 
-    /// El programa SPL Token
     #[account(address = anchor_spl::token::ID)]
     pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
 }
 
+/// Atomic purchase of a listed position
 #[derive(Accounts)]
-pub struct CloseContract<'info> {
+pub struct BuyPosition<'info> {
     #[account(
-        mut,
         seeds = [b"contract", contract.admin.as_ref(), &contract.contract_id.to_le_bytes()],
         bump
     )]
-    pub contract: Account<'info, Contract>,
-    pub admin: Signer<'info>,
-}
+    pub contract: Box<Account<'info, Contract>>,
 
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Box<Account<'info, Config>>,
 
-/// Admin prolong => add 2 weeks
-#[derive(Accounts)]
-pub struct ProlongContract<'info> {
+    #[account(
+        seeds = [
+            b"investor-record",
+            contract.key().as_ref(),
+            investor_record.investor.as_ref()
+        ],
+        bump = investor_record.bump,
+        has_one = contract
+    )]
+    pub investor_record: Box<Account<'info, InvestorRecord>>,
+
+    // Rent goes back to the seller
     #[account(
         mut,
-        seeds = [b"contract", contract.admin.as_ref(), &contract.contract_id.to_le_bytes()],
-        bump
+        close = seller,
+        seeds = [b"listing", investor_record.key().as_ref()],
+        bump = listing.bump,
+        has_one = contract,
+        has_one = seller
     )]
-    pub contract: Account<'info, Contract>,
+    pub listing: Box<Account<'info, Listing>>,
 
+    /// CHECK: listing.seller, only receives the listing rent
     #[account(mut)]
-    pub admin: Signer<'info>,
+    pub seller: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    #[account(address = investor_record.nft_mint)]
+    pub position_mint: Box<Account<'info, Mint>>,
+
+    #[account(mut, address = listing.position_token_account)]
+    pub seller_position_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        associated_token::mint = position_mint,
+        associated_token::authority = buyer,
+    )]
+    pub buyer_position_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut, token::mint = contract.token_mint)]
+    pub buyer_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        token::mint = contract.token_mint,
+        token::authority = seller
+    )]
+    pub seller_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        token::mint = contract.token_mint,
+        token::authority = config.fee_recipient
+    )]
+    pub fee_recipient_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(address = anchor_spl::token::ID)]
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
 }
 
-/// Default if not repaid after prolongation
 #[derive(Accounts)]
-pub struct DefaultContract<'info> {
+pub struct CancelListing<'info> {
     #[account(
-        mut,
         seeds = [b"contract", contract.admin.as_ref(), &contract.contract_id.to_le_bytes()],
         bump
     )]
     pub contract: Account<'info, Contract>,
-    pub system_program: Program<'info, System>,
+
+    #[account(
+        mut,
+        close = seller,
+        seeds = [b"listing", listing.investor_record.as_ref()],
+        bump = listing.bump,
+        has_one = contract,
+        has_one = seller
+    )]
+    pub listing: Account<'info, Listing>,
+
+    /// CHECK: listing.seller, only receives the listing rent
+    #[account(mut)]
+    pub seller: AccountInfo<'info>,
+
+    #[account(mut, address = listing.position_token_account)]
+    pub seller_position_account: Account<'info, TokenAccount>,
+
+    pub caller: Signer<'info>,
+
+    #[account(address = anchor_spl::token::ID)]
+    pub token_program: Program<'info, Token>,
+}
+
+// ---------------------------------------------------------------------
+// Events
+// ---------------------------------------------------------------------
+#[event]
+pub struct PauseChanged {
+    /// None for the protocol-wide flag
+    pub contract: Option<Pubkey>,
+    pub paused: bool,
+    pub by: Pubkey,
+    pub reason: String,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ProlongationDecided {
+    pub contract: Pubkey,
+    pub round: u8,
+    pub approved: bool,
+    pub extension_seconds: i64,
+    pub sweetener_bps: u16,
+    pub votes_for: u64,
+    pub votes_against: u64,
+}
+
+#[event]
+pub struct RecoveryDeposited {
+    pub contract: Pubkey,
+    /// The contract itself when the leftover buyback is swept in at default
+    pub depositor: Pubkey,
+    pub amount: u64,
+    pub total_recovered: u64,
+}
+
+#[event]
+pub struct PositionSold {
+    pub contract: Pubkey,
+    pub investor_record: Pubkey,
+    pub seller: Pubkey,
+    pub buyer: Pubkey,
+    pub price: u64,
+    /// Protocol cut (config.fee_bps), taken out of the price
+    pub fee: u64,
+}
+
+#[event]
+pub struct InstallmentMissed {
+    pub contract: Pubkey,
+    pub index: u8,
+    pub due_time: i64,
+    pub outstanding: u64,
 }
 
 // ---------------------------------------------------------------------
@@ -868,4 +4028,376 @@ pub enum ErrorCode {
     InvalidStateForProlongOrDefault,
     #[msg("NFT already claimed")]
     NftAlreadyClaimed,
-}
\ No newline at end of file
+    #[msg("Investor already refunded")]
+    AlreadyRefunded,
+    #[msg("Nothing to refund")]
+    NothingToRefund,
+    #[msg("Deposit exceeds the buyback owed")]
+    ExceedsBuyback,
+    #[msg("Investor already settled")]
+    AlreadySettled,
+    #[msg("Not every investor has been settled")]
+    InvestorsNotSettled,
+    #[msg("Admin window not expired yet")]
+    AdminWindowNotExpired,
+    #[msg("Timing window out of bounds")]
+    InvalidWindow,
+    #[msg("Too many allowed mints")]
+    TooManyMints,
+    #[msg("Fee out of bounds")]
+    InvalidFee,
+    #[msg("Invalid multisig signers or threshold")]
+    InvalidMultisig,
+    #[msg("Withdrawal proposal expired")]
+    ProposalExpired,
+    #[msg("Signer already approved")]
+    AlreadyApproved,
+    #[msg("Not enough approvals")]
+    NotEnoughApprovals,
+    #[msg("Proposal does not match the escrow")]
+    ProposalMismatch,
+    #[msg("Paused")]
+    Paused,
+    #[msg("Rate out of bounds")]
+    InvalidRate,
+    #[msg("Math overflow")]
+    MathOverflow,
+    #[msg("Invalid repayment schedule")]
+    InvalidSchedule,
+    #[msg("Contract is repaid through its installment schedule")]
+    ScheduledContract,
+    #[msg("Nothing to claim")]
+    NothingToClaim,
+    #[msg("Contract already matured")]
+    ContractMatured,
+    #[msg("Prolongation cap reached")]
+    TooManyProlongations,
+    #[msg("Voting window closed")]
+    VotingClosed,
+    #[msg("Vote still open")]
+    VotingOpen,
+    #[msg("Buyback vault must be passed to sweep it")]
+    MissingBuybackVault,
+    #[msg("Funding target not reached")]
+    FundingIncomplete,
+    #[msg("Funding already verified")]
+    AlreadyVerified,
+    #[msg("Funding not verified yet")]
+    NotVerified,
+    #[msg("Minimum raise above the funding target")]
+    InvalidMinimumRaise,
+    #[msg("NFT metadata field too long")]
+    InvalidMetadata,
+    #[msg("Caller does not hold the position NFT")]
+    NotPositionHolder,
+    #[msg("Listing can no longer be filled")]
+    ListingStale,
+    #[msg("Buyback vault already covers the whole buyback")]
+    BuybackCovered,
+    #[msg("Listing price above the buyer's limit")]
+    PriceAboveLimit,
+    #[msg("Repayment schedule must be passed")]
+    MissingSchedule,
+    #[msg("Repayment schedule already paid in full")]
+    ScheduleComplete,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_spl::token::spl_token::{
+        self, solana_program::program_pack::Pack, state::AccountState,
+    };
+
+    const NOW: i64 = 1_700_000_000;
+    const COOLOFF: i64 = 86400;
+
+    /// Every field zeroed (empty strings, first enum variants).
+    fn zeroed<T: AnchorDeserialize>() -> T {
+        T::deserialize(&mut &[0u8; 1024][..]).unwrap()
+    }
+
+    fn contract() -> Contract {
+        let mut contract: Contract = zeroed();
+        contract.start_time = NOW;
+        contract.duration = 90 * 86400;
+        contract.buyback_window = 14 * 86400;
+        contract.prolong_extension = 30 * 86400;
+        contract
+    }
+
+    fn matured(mut contract: Contract) -> Contract {
+        contract.status = ContractStatus::PendingBuyback;
+        contract.buyback_deadline = contract.start_time + contract.duration + contract.buyback_window;
+        contract
+    }
+
+    fn prolonged(mut contract: Contract) -> Contract {
+        contract = matured(contract);
+        contract.status = ContractStatus::Prolonged;
+        contract.prolonged_deadline = contract.buyback_deadline + contract.prolong_extension;
+        contract
+    }
+
+    fn packed_token_account(
+        mint: Pubkey,
+        owner: Pubkey,
+        amount: u64,
+        delegate: Option<Pubkey>,
+    ) -> [u8; spl_token::state::Account::LEN] {
+        let state = spl_token::state::Account {
+            mint,
+            owner,
+            amount,
+            delegate: delegate.into(),
+            state: AccountState::Initialized,
+            delegated_amount: if delegate.is_some() { amount } else { 0 },
+            ..Default::default()
+        };
+        let mut data = [0u8; spl_token::state::Account::LEN];
+        spl_token::state::Account::pack(state, &mut data).unwrap();
+        data
+    }
+
+    fn token_account(mint: Pubkey, owner: Pubkey, amount: u64, delegate: Option<Pubkey>) -> TokenAccount {
+        let data = packed_token_account(mint, owner, amount, delegate);
+        TokenAccount::try_deserialize_unchecked(&mut &data[..]).unwrap()
+    }
+
+    // -- Withdrawal cool-off --------------------------------------------
+
+    #[test]
+    fn withdrawal_inside_cooloff_is_fee_free() {
+        let mut record: InvestorRecord = zeroed();
+        record.add_to_cooloff(1_000, NOW, COOLOFF).unwrap();
+
+        // The boundary itself is still inside the window
+        assert_eq!(record.take_withdrawal_fee(400, NOW + COOLOFF, COOLOFF, 100).unwrap(), 0);
+        assert_eq!(record.cooloff_amount, 600);
+    }
+
+    #[test]
+    fn withdrawal_after_cooloff_pays_the_fee() {
+        let mut record: InvestorRecord = zeroed();
+        record.add_to_cooloff(1_000, NOW, COOLOFF).unwrap();
+
+        // 1% of 401 rounds up to 5
+        assert_eq!(record.take_withdrawal_fee(401, NOW + COOLOFF + 1, COOLOFF, 100).unwrap(), 5);
+        assert_eq!(record.cooloff_amount, 0);
+    }
+
+    #[test]
+    fn only_principal_older_than_the_cooloff_pays_the_fee() {
+        let mut record: InvestorRecord = zeroed();
+        record.add_to_cooloff(1_000, NOW, COOLOFF).unwrap();
+        // A later tranche opens a new window covering only itself
+        let later = NOW + 2 * COOLOFF;
+        record.add_to_cooloff(500, later, COOLOFF).unwrap();
+        assert_eq!(record.invested_at, later);
+        assert_eq!(record.cooloff_amount, 500);
+
+        // 500 fresh leaves fee-free, the 300 older pays 1%
+        assert_eq!(record.take_withdrawal_fee(800, later + 10, COOLOFF, 100).unwrap(), 3);
+        assert_eq!(record.cooloff_amount, 0);
+    }
+
+    #[test]
+    fn top_up_inside_cooloff_does_not_extend_it() {
+        let mut record: InvestorRecord = zeroed();
+        record.add_to_cooloff(1_000, NOW, COOLOFF).unwrap();
+        record.add_to_cooloff(1_000, NOW + COOLOFF - 1, COOLOFF).unwrap();
+        assert_eq!(record.invested_at, NOW);
+        assert_eq!(record.cooloff_amount, 2_000);
+
+        assert_eq!(record.take_withdrawal_fee(2_000, NOW + COOLOFF, COOLOFF, 100).unwrap(), 0);
+        record.add_to_cooloff(2_000, NOW + COOLOFF, COOLOFF).unwrap();
+        assert_eq!(record.take_withdrawal_fee(2_000, NOW + COOLOFF + 1, COOLOFF, 100).unwrap(), 20);
+    }
+
+    #[test]
+    fn fee_free_allowance_is_used_up() {
+        let mut record: InvestorRecord = zeroed();
+        record.add_to_cooloff(1_000, NOW, COOLOFF).unwrap();
+
+        assert_eq!(record.take_withdrawal_fee(600, NOW + 10, COOLOFF, 100).unwrap(), 0);
+        // Only the 400 left in the window is still fee-free
+        assert_eq!(record.take_withdrawal_fee(600, NOW + 20, COOLOFF, 100).unwrap(), 2);
+        assert_eq!(record.cooloff_amount, 0);
+    }
+
+    #[test]
+    fn zero_cooloff_charges_every_withdrawal() {
+        let mut record: InvestorRecord = zeroed();
+        record.add_to_cooloff(1_000, NOW, 0).unwrap();
+        assert_eq!(record.take_withdrawal_fee(1_000, NOW + 1, 0, 50).unwrap(), 5);
+    }
+
+    // -- Accrual and penalties ------------------------------------------
+
+    #[test]
+    fn accrual_runs_to_until_while_active() {
+        let contract = contract();
+        assert_eq!(contract.accrual_seconds(NOW + 1_000), 1_000);
+        assert_eq!(contract.accrual_seconds(NOW - 1_000), 0);
+    }
+
+    #[test]
+    fn accrual_stops_at_the_settlement_deadline() {
+        let contract = matured(contract());
+        let deadline = contract.buyback_deadline;
+        assert_eq!(contract.accrual_seconds(deadline + 5_000), deadline - NOW);
+
+        let contract = prolonged(contract);
+        let deadline = contract.prolonged_deadline;
+        assert_eq!(contract.accrual_seconds(deadline + 5_000), deadline - NOW);
+    }
+
+    #[test]
+    fn accrual_stops_once_the_buyback_is_covered() {
+        let mut contract = matured(contract());
+        contract.buyback_covered_at = NOW + contract.duration + 100;
+        assert_eq!(contract.accrual_seconds(contract.buyback_deadline), contract.duration + 100);
+    }
+
+    #[test]
+    fn accrual_follows_the_prepayment_policy() {
+        let mut contract = matured(contract());
+        contract.early_repaid_at = NOW + 10 * 86400;
+
+        contract.prepayment_policy = PrepaymentPolicy::Full;
+        assert_eq!(contract.accrual_seconds(contract.buyback_deadline), contract.duration);
+
+        contract.prepayment_policy = PrepaymentPolicy::Prorated;
+        assert_eq!(contract.accrual_seconds(contract.buyback_deadline), 10 * 86400);
+    }
+
+    #[test]
+    fn late_penalty_only_while_prolonged() {
+        let mut contract = matured(contract());
+        contract.late_penalty_bps_per_day = 10;
+        assert_eq!(
+            contract.late_penalty(1_000_000, contract.buyback_deadline + 86400, Rounding::Up).unwrap(),
+            0
+        );
+
+        let contract = prolonged(contract);
+        // 10 bps a day on 1_000_000 for two days
+        let until = contract.buyback_deadline + 2 * 86400;
+        assert_eq!(contract.late_penalty(1_000_000, until, Rounding::Down).unwrap(), 2_000);
+    }
+
+    #[test]
+    fn late_penalty_rounds_and_caps() {
+        let mut contract = prolonged(contract());
+        contract.late_penalty_bps_per_day = 10;
+
+        let until = contract.buyback_deadline + 1;
+        assert_eq!(contract.late_penalty(1_000_000, until, Rounding::Down).unwrap(), 0);
+        assert_eq!(contract.late_penalty(1_000_000, until, Rounding::Up).unwrap(), 1);
+
+        // Nothing accrues past the prolonged deadline or the covered time
+        let full = (contract.prolonged_deadline - contract.buyback_deadline) as u64;
+        let capped = contract
+            .late_penalty(1_000_000, contract.prolonged_deadline + 86400, Rounding::Down)
+            .unwrap();
+        assert_eq!(capped, 1_000_000 * 10 * full / (10_000 * 86400));
+
+        contract.buyback_covered_at = contract.buyback_deadline + 86400;
+        assert_eq!(
+            contract.late_penalty(1_000_000, contract.prolonged_deadline, Rounding::Down).unwrap(),
+            1_000
+        );
+    }
+
+    #[test]
+    fn latest_repayment_time_assumes_every_remaining_prolongation() {
+        let mut contract = contract();
+        let maturity = NOW + contract.duration;
+        assert_eq!(
+            contract.latest_repayment_time(),
+            maturity + contract.buyback_window + 3 * contract.prolong_extension
+        );
+
+        contract = prolonged(contract);
+        contract.prolongation_count = 1;
+        assert_eq!(
+            contract.latest_repayment_time(),
+            contract.prolonged_deadline + 2 * contract.prolong_extension
+        );
+
+        contract.prolongation_count = MAX_PROLONGATIONS;
+        assert_eq!(contract.latest_repayment_time(), contract.prolonged_deadline);
+    }
+
+    // -- Positions and listings -----------------------------------------
+
+    #[test]
+    fn payee_is_the_investor_before_the_nft_is_minted() {
+        let mut record: InvestorRecord = zeroed();
+        record.investor = Pubkey::new_unique();
+        assert_eq!(record.payee(None).unwrap(), record.investor);
+    }
+
+    #[test]
+    fn payee_is_the_nft_holder_once_minted() {
+        let mut record: InvestorRecord = zeroed();
+        record.investor = Pubkey::new_unique();
+        record.nft_minted = true;
+        record.nft_mint = Pubkey::new_unique();
+        let holder = Pubkey::new_unique();
+        let not_holder = error!(ErrorCode::NotPositionHolder);
+
+        assert_eq!(record.payee(None).unwrap_err(), not_holder);
+
+        let key = Pubkey::new_unique();
+        let owner = anchor_spl::token::ID;
+        let cases = [
+            (record.nft_mint, 1, Ok(holder)),
+            (record.nft_mint, 0, Err(())),
+            (Pubkey::new_unique(), 1, Err(())),
+        ];
+        for (mint, amount, expected) in cases {
+            let mut data = packed_token_account(mint, holder, amount, None);
+            let mut lamports = 1;
+            let info = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+            let position = Account::<TokenAccount>::try_from(&info).unwrap();
+            match expected {
+                Ok(payee) => assert_eq!(record.payee(Some(&position)).unwrap(), payee),
+                Err(()) => assert_eq!(record.payee(Some(&position)).unwrap_err(), not_holder),
+            }
+        }
+    }
+
+    #[test]
+    fn listing_is_live_while_the_seller_keeps_nft_and_delegation() {
+        let mut listing: Listing = zeroed();
+        listing.seller = Pubkey::new_unique();
+        let address = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+
+        assert!(listing.is_live(address, &token_account(mint, listing.seller, 1, Some(address))));
+        // Sold elsewhere, delegation revoked or handed to someone else
+        assert!(!listing.is_live(address, &token_account(mint, Pubkey::new_unique(), 1, Some(address))));
+        assert!(!listing.is_live(address, &token_account(mint, listing.seller, 0, Some(address))));
+        assert!(!listing.is_live(address, &token_account(mint, listing.seller, 1, None)));
+        assert!(!listing.is_live(
+            address,
+            &token_account(mint, listing.seller, 1, Some(Pubkey::new_unique()))
+        ));
+    }
+
+    #[test]
+    fn position_nft_name_fits_metaplex() {
+        let mut contract = contract();
+        contract.contract_id = 7;
+        contract.farm_name = "Finca El Roble".to_string();
+        assert_eq!(contract.position_nft_name(), "Finca El Roble #7");
+
+        contract.contract_id = 12345;
+        contract.farm_name = "Cooperativa Agrícola del Valle Central".to_string();
+        let name = contract.position_nft_name();
+        assert!(name.len() <= MAX_NFT_NAME_LEN);
+        assert!(name.ends_with(" #12345"));
+        assert!(name.starts_with("Cooperativa Agr"));
+    }
+}
@@ -0,0 +1,190 @@
+//! Fixed-point helpers shared by every quote and payout.
+//!
+//! Amounts are raw token units (`u64`) and rates are basis points. Products are
+//! widened to `u128` before the single final division, and every division says
+//! which way it rounds. Rounding always favours the vault: what the program
+//! pays out rounds down, what it asks the borrower for rounds up.
+
+use anchor_lang::prelude::*;
+
+use crate::ErrorCode;
+
+pub const BPS_DENOMINATOR: u64 = 10_000;
+/// Actual/365 Fixed: every year is 365 days of 86 400 seconds.
+pub const SECONDS_PER_YEAR: i64 = 365 * 86400;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Rounding {
+    /// Paying out of a vault
+    Down,
+    /// Quoting what has to be paid in
+    Up,
+}
+
+fn div_round(numerator: u128, denominator: u128, rounding: Rounding) -> Result<u64> {
+    require!(denominator != 0, ErrorCode::MathOverflow);
+    let quotient = match rounding {
+        Rounding::Down => numerator / denominator,
+        Rounding::Up => numerator.div_ceil(denominator),
+    };
+    u64::try_from(quotient).map_err(|_| error!(ErrorCode::MathOverflow))
+}
+
+/// `a * b / denominator` without intermediate overflow.
+pub fn mul_div(a: u64, b: u64, denominator: u64, rounding: Rounding) -> Result<u64> {
+    div_round(a as u128 * b as u128, denominator as u128, rounding)
+}
+
+/// `rate_bps` basis points of `amount`.
+pub fn bps_of(amount: u64, rate_bps: u64, rounding: Rounding) -> Result<u64> {
+    mul_div(amount, rate_bps, BPS_DENOMINATOR, rounding)
+}
+
+/// Simple interest on `principal` at `apr_bps` per year over `elapsed` seconds.
+pub fn accrue(principal: u64, apr_bps: u64, elapsed: i64, rounding: Rounding) -> Result<u64> {
+    let elapsed = elapsed.max(0) as u128;
+    let numerator = (principal as u128)
+        .checked_mul(apr_bps as u128)
+        .and_then(|n| n.checked_mul(elapsed))
+        .ok_or(ErrorCode::MathOverflow)?;
+    div_round(
+        numerator,
+        BPS_DENOMINATOR as u128 * SECONDS_PER_YEAR as u128,
+        rounding,
+    )
+}
+
+pub fn add(a: u64, b: u64) -> Result<u64> {
+    a.checked_add(b).ok_or_else(|| error!(ErrorCode::MathOverflow))
+}
+
+pub fn sub(a: u64, b: u64) -> Result<u64> {
+    a.checked_sub(b).ok_or_else(|| error!(ErrorCode::MathOverflow))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Contract, ContractStatus, PrepaymentPolicy};
+
+    fn overflow() -> Error {
+        error!(ErrorCode::MathOverflow)
+    }
+
+    #[test]
+    fn mul_div_rounds_as_asked() {
+        assert_eq!(mul_div(10, 1, 3, Rounding::Down).unwrap(), 3);
+        assert_eq!(mul_div(10, 1, 3, Rounding::Up).unwrap(), 4);
+        // Exact quotients are the same either way
+        assert_eq!(mul_div(9, 1, 3, Rounding::Down).unwrap(), 3);
+        assert_eq!(mul_div(9, 1, 3, Rounding::Up).unwrap(), 3);
+    }
+
+    #[test]
+    fn mul_div_widens_the_product() {
+        assert_eq!(mul_div(u64::MAX, u64::MAX, u64::MAX, Rounding::Down).unwrap(), u64::MAX);
+    }
+
+    #[test]
+    fn mul_div_overflow() {
+        assert_eq!(mul_div(u64::MAX, 2, 1, Rounding::Down).unwrap_err(), overflow());
+        assert_eq!(mul_div(1, 1, 0, Rounding::Up).unwrap_err(), overflow());
+    }
+
+    #[test]
+    fn bps_of_rounds_as_asked() {
+        assert_eq!(bps_of(10_000, 250, Rounding::Down).unwrap(), 250);
+        assert_eq!(bps_of(1, 1, Rounding::Down).unwrap(), 0);
+        assert_eq!(bps_of(1, 1, Rounding::Up).unwrap(), 1);
+        assert_eq!(bps_of(12_345, 100, Rounding::Down).unwrap(), 123);
+        assert_eq!(bps_of(12_345, 100, Rounding::Up).unwrap(), 124);
+    }
+
+    #[test]
+    fn bps_of_overflow() {
+        assert_eq!(bps_of(u64::MAX, 20_000, Rounding::Down).unwrap_err(), overflow());
+    }
+
+    #[test]
+    fn accrue_rounds_as_asked() {
+        // 10% APR on 1_000_000 for a full year
+        assert_eq!(accrue(1_000_000, 1_000, SECONDS_PER_YEAR, Rounding::Down).unwrap(), 100_000);
+        assert_eq!(accrue(1_000_000, 1_000, SECONDS_PER_YEAR, Rounding::Up).unwrap(), 100_000);
+        // One second of it is a fraction of a unit
+        assert_eq!(accrue(1_000_000, 1_000, 1, Rounding::Down).unwrap(), 0);
+        assert_eq!(accrue(1_000_000, 1_000, 1, Rounding::Up).unwrap(), 1);
+    }
+
+    #[test]
+    fn accrue_ignores_negative_time() {
+        assert_eq!(accrue(1_000_000, 1_000, -86400, Rounding::Up).unwrap(), 0);
+    }
+
+    #[test]
+    fn accrue_overflow() {
+        assert_eq!(accrue(u64::MAX, u64::MAX, i64::MAX, Rounding::Down).unwrap_err(), overflow());
+        // Fits in u128 but the result doesn't fit in u64
+        assert_eq!(
+            accrue(u64::MAX, 10_000, 2 * SECONDS_PER_YEAR, Rounding::Down).unwrap_err(),
+            overflow()
+        );
+    }
+
+    #[test]
+    fn add_sub_overflow() {
+        assert_eq!(add(u64::MAX, 1).unwrap_err(), overflow());
+        assert_eq!(sub(0, 1).unwrap_err(), overflow());
+    }
+
+    /// Every field zeroed (empty strings, first enum variants), then matured.
+    fn matured_contract(principals: &[u64]) -> Contract {
+        let mut contract = Contract::deserialize(&mut &[0u8; 1024][..]).unwrap();
+        contract.amount_funded_so_far = principals.iter().sum();
+        contract.apr_bps = 1_237;
+        contract.start_time = 1_000;
+        contract.duration = 90 * 86400 + 17;
+        contract.status = ContractStatus::PendingBuyback;
+        contract.buyback_deadline = contract.start_time + contract.duration + 14 * 86400;
+        contract
+    }
+
+    fn assert_payouts_within_buyback(contract: &Contract, principals: &[u64], until: i64) {
+        let mut paid = 0;
+        for &principal in principals {
+            paid = add(paid, contract.investor_payout(principal, until).unwrap()).unwrap();
+        }
+        assert!(paid <= contract.calculate_buyback(until).unwrap());
+    }
+
+    #[test]
+    fn investor_payouts_never_exceed_buyback() {
+        // Uneven splits so every per-investor division leaves a remainder
+        let mut principals = vec![1, 3, 7, 999_999, 1_234_567, 10_000_001];
+        let mut seed: u64 = 0x2545_f491;
+        for _ in 0..50 {
+            seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+            principals.push(seed % 5_000_000_000 + 1);
+        }
+
+        let mut contract = matured_contract(&principals);
+        let maturity = contract.start_time + contract.duration;
+        for until in [maturity, maturity + 1, maturity + 86_399, contract.buyback_deadline] {
+            assert_payouts_within_buyback(&contract, &principals, until);
+        }
+
+        // Prolonged with a sweetener and late penalty
+        contract.status = ContractStatus::Prolonged;
+        contract.prolonged_deadline = contract.buyback_deadline + 30 * 86400;
+        contract.extension_bonus_bps = 37;
+        contract.late_penalty_bps_per_day = 3;
+        assert_payouts_within_buyback(&contract, &principals, contract.buyback_deadline + 12_345);
+
+        // Repaid early with a prepayment penalty
+        contract.status = ContractStatus::PendingBuyback;
+        contract.extension_bonus_bps = 0;
+        contract.prepayment_policy = PrepaymentPolicy::ProratedWithPenalty;
+        contract.prepayment_penalty_bps = 113;
+        contract.early_repaid_at = contract.start_time + 45 * 86400 + 7;
+        assert_payouts_within_buyback(&contract, &principals, contract.early_repaid_at);
+    }
+}
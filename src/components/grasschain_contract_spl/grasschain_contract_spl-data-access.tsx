@@ -15,6 +15,7 @@ import toast from "react-hot-toast";
 import {
   TOKEN_PROGRAM_ID,
   ASSOCIATED_TOKEN_PROGRAM_ID,
+  getAssociatedTokenAddressSync,
 } from "@solana/spl-token";
 
 import grasschainSplIdl from "../../../anchor/target/idl/grasschain_contract_spl.json";
@@ -29,6 +30,49 @@ export const USDC_DEVNET_MINT = new PublicKey(
   "Gh9ZwEmdLJ8DscKNTkTqPbNwLNNBjuSzaG9Vp2KGtKJr"
 );
 
+// Ventanas por defecto (segundos) al crear un contrato
+export const DEFAULT_FUNDING_WINDOW = 30 * 86400;
+export const DEFAULT_ADMIN_WINDOW = 30 * 86400;
+export const DEFAULT_BUYBACK_WINDOW = 30 * 86400;
+export const DEFAULT_PROLONG_EXTENSION = 14 * 86400;
+
+// Mint del NFT de la posición: uno por InvestorRecord, autoridad = PDA del contrato
+export function getPositionMint(
+  programId: PublicKey,
+  contractPk: PublicKey,
+  investor: PublicKey
+): PublicKey {
+  const [investorRecordPda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("investor-record"), contractPk.toBuffer(), investor.toBuffer()],
+    programId
+  );
+  const [mint] = PublicKey.findProgramAddressSync(
+    [Buffer.from("position-mint"), investorRecordPda.toBuffer()],
+    programId
+  );
+  return mint;
+}
+
+// PDAs de metadata y master edition de Metaplex para un mint
+function getMetadataPda(mint: PublicKey): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from("metadata"), TOKEN_METADATA_PROGRAM_ID.toBuffer(), mint.toBuffer()],
+    TOKEN_METADATA_PROGRAM_ID
+  )[0];
+}
+
+function getMasterEditionPda(mint: PublicKey): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    [
+      Buffer.from("metadata"),
+      TOKEN_METADATA_PROGRAM_ID.toBuffer(),
+      mint.toBuffer(),
+      Buffer.from("edition"),
+    ],
+    TOKEN_METADATA_PROGRAM_ID
+  )[0];
+}
+
 // Crea el objeto Program de Anchor
 function getProgram(provider: AnchorProvider): Program<GrasschainContractSpl> {
   return new Program<GrasschainContractSpl>(
@@ -87,27 +131,99 @@ export function useGrasschainContractSplProgram() {
     });
   }
 
+  // HOOK para obtener los Listings (mercado secundario) de un contrato.
+  function useListings(contractPk: PublicKey) {
+    return useQuery({
+      queryKey: ["listings", contractPk?.toBase58()],
+      queryFn: async () => {
+        return program.account.listing.all([
+          {
+            memcmp: {
+              offset: 8, // 'contract' va justo después del discriminator
+              bytes: contractPk.toBase58(),
+            },
+          },
+        ]);
+      },
+      enabled: !!program && !!contractPk,
+    });
+  }
+
+  // Titular actual de una posición: quien tenga el NFT si ya se minteó,
+  // si no el inversor original. Devuelve su ATA de pago y la cuenta del NFT.
+  async function findPositionHolder(contractPk: PublicKey, investorPk: PublicKey) {
+    const [investorRecordPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("investor-record"), contractPk.toBuffer(), investorPk.toBuffer()],
+      program.programId
+    );
+    const record = await program.account.investorRecord.fetch(investorRecordPda);
+    const contractData = await program.account.contract.fetch(contractPk);
+
+    let holder = investorPk;
+    let positionTokenAccount: PublicKey | null = null;
+    if (record.nftMinted) {
+      const largest = await connection.getTokenLargestAccounts(record.nftMint);
+      const held = largest.value.find((a) => a.uiAmount === 1);
+      if (!held) throw new Error("Position NFT holder not found.");
+      const info = await connection.getParsedAccountInfo(held.address);
+      holder = new PublicKey((info.value?.data as any).parsed.info.owner);
+      positionTokenAccount = held.address;
+    }
+    const payoutTokenAccount = getAssociatedTokenAddressSync(
+      contractData.tokenMint,
+      holder,
+      true
+    );
+    // Hace falta para congelar el NFT una vez pagada la posición
+    const positionMint = record.nftMinted ? record.nftMint : null;
+    return {
+      investorRecordPda,
+      holder,
+      positionTokenAccount,
+      positionMint,
+      payoutTokenAccount,
+    };
+  }
+
   // MUTACIONES
   interface CreateContractArgs {
-    nftMint: PublicKey;
     totalInvestmentNeeded: number;
-    yieldPercentage: number;
+    aprBps: number;
     durationInSeconds: number;
     contractId: number;
     farmName: string;
     farmAddress: string;
     farmImageUrl: string;
+    fundingWindow?: number;
+    adminWindow?: number;
+    buybackWindow?: number;
+    prolongExtension?: number;
+    // Farmer co-signing the terms; defaults to the connected wallet
+    borrower?: PublicKey;
+    prepaymentPolicy?: { full: {} } | { prorated: {} } | { proratedWithPenalty: {} };
+    prepaymentPenaltyBps?: number;
+    latePenaltyBpsPerDay?: number;
+    // Soft cap in token units; 0 keeps the raise all-or-nothing
+    minimumRaise?: number;
   }
   const createContract = useMutation<string, Error, CreateContractArgs>({
     mutationFn: async ({
-      nftMint,
       totalInvestmentNeeded,
-      yieldPercentage,
+      aprBps,
       durationInSeconds,
       contractId,
       farmName,
       farmAddress,
       farmImageUrl,
+      fundingWindow = DEFAULT_FUNDING_WINDOW,
+      adminWindow = DEFAULT_ADMIN_WINDOW,
+      buybackWindow = DEFAULT_BUYBACK_WINDOW,
+      prolongExtension = DEFAULT_PROLONG_EXTENSION,
+      borrower,
+      prepaymentPolicy = { prorated: {} },
+      prepaymentPenaltyBps = 0,
+      latePenaltyBpsPerDay = 0,
+      minimumRaise = 0,
     }) => {
       if (!publicKey) throw new Error("No wallet connected.");
 
@@ -123,26 +239,49 @@ export function useGrasschainContractSplProgram() {
         [Buffer.from("escrow-vault"), contractPda.toBuffer()],
         program.programId
       );
+      // La colección del contrato pasa a ser contract.nftMint
+      const [collectionMint] = PublicKey.findProgramAddressSync(
+        [Buffer.from("collection"), contractPda.toBuffer()],
+        program.programId
+      );
 
       const txSig = await program.methods
         .createContract(
           new BN(totalInvestmentNeeded),
-          new BN(yieldPercentage),
+          aprBps,
           new BN(durationInSeconds),
           new BN(contractId),
-          nftMint,
           farmName,
           farmAddress,
-          farmImageUrl
+          farmImageUrl,
+          new BN(fundingWindow),
+          new BN(adminWindow),
+          new BN(buybackWindow),
+          new BN(prolongExtension),
+          prepaymentPolicy as any,
+          prepaymentPenaltyBps,
+          latePenaltyBpsPerDay,
+          new BN(minimumRaise)
         )
         .accountsPartial({
           admin: publicKey,
+          borrower: borrower ?? publicKey,
           tokenMint: USDC_DEVNET_MINT,
           contract: contractPda,
           escrowVault: escrowVaultPda,
+          collectionMint,
+          collectionTokenAccount: getAssociatedTokenAddressSync(
+            collectionMint,
+            contractPda,
+            true
+          ),
+          collectionMetadata: getMetadataPda(collectionMint),
+          collectionMasterEdition: getMasterEditionPda(collectionMint),
           tokenProgram: TOKEN_PROGRAM_ID,
           associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          tokenMetadataProgram: TOKEN_METADATA_PROGRAM_ID,
           systemProgram: SystemProgram.programId,
+          rent: SYSVAR_RENT_PUBKEY,
         })
         .rpc();
 
@@ -194,6 +333,85 @@ export function useGrasschainContractSplProgram() {
     },
   });
 
+  interface WithdrawInvestmentArgs {
+    contractPk: PublicKey;
+    amount: number;
+    investorTokenAccount: PublicKey;
+  }
+  const withdrawInvestment = useMutation<string, Error, WithdrawInvestmentArgs>({
+    mutationFn: async ({ contractPk, amount, investorTokenAccount }) => {
+      if (!publicKey) throw new Error("No wallet connected.");
+
+      const contractData = await program.account.contract.fetch(contractPk);
+      const escrowVault = contractData.escrowTokenAccount as PublicKey;
+
+      const [configPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("config")],
+        program.programId
+      );
+      const config = await program.account.config.fetch(configPda);
+      // Exit fees land in the fee recipient's ATA
+      const feeRecipientTokenAccount = getAssociatedTokenAddressSync(
+        contractData.tokenMint,
+        config.feeRecipient,
+        true
+      );
+
+      const [investorRecordPda] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("investor-record"),
+          contractPk.toBuffer(),
+          publicKey.toBuffer(),
+        ],
+        program.programId
+      );
+
+      const txSig = await program.methods
+        .withdrawInvestment(new BN(amount))
+        .accountsPartial({
+          contract: contractPk,
+          investor: publicKey,
+          investorRecord: investorRecordPda,
+          escrowVault,
+          investorTokenAccount,
+          feeRecipientTokenAccount,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+
+      toast.success("withdrawInvestment success: " + txSig);
+      queryClient.invalidateQueries(["investorRecords", contractPk.toBase58()] as any);
+      allContracts.refetch();
+      return txSig;
+    },
+  });
+
+  interface SetWithdrawalTermsArgs {
+    contractPk: PublicKey;
+    cooloffSeconds: number;
+    exitFeeBps: number;
+    // Farmer co-signing the terms; defaults to the connected wallet
+    borrower?: PublicKey;
+  }
+  const setWithdrawalTerms = useMutation<string, Error, SetWithdrawalTermsArgs>({
+    mutationFn: async ({ contractPk, cooloffSeconds, exitFeeBps, borrower }) => {
+      if (!publicKey) throw new Error("No wallet connected.");
+
+      const txSig = await program.methods
+        .setWithdrawalTerms(new BN(cooloffSeconds), exitFeeBps)
+        .accountsPartial({
+          contract: contractPk,
+          admin: publicKey,
+          borrower: borrower ?? publicKey,
+        })
+        .rpc();
+
+      toast.success("setWithdrawalTerms success: " + txSig);
+      allContracts.refetch();
+      return txSig;
+    },
+  });
+
   interface VerifyFundingArgs {
     contractPk: PublicKey;
   }
@@ -218,25 +436,9 @@ export function useGrasschainContractSplProgram() {
 
   interface ClaimNftArgs {
     contractPk: PublicKey;
-    mint: Keypair;
-    associatedTokenAccount: PublicKey;
-    metadataAccount: PublicKey;
-    masterEditionAccount: PublicKey;
-    name: string;
-    symbol: string;
-    uri: string;
   }
   const claimNft = useMutation<string, Error, ClaimNftArgs>({
-    mutationFn: async ({
-      contractPk,
-      mint,
-      associatedTokenAccount,
-      metadataAccount,
-      masterEditionAccount,
-      name,
-      symbol,
-      uri,
-    }) => {
+    mutationFn: async ({ contractPk }) => {
       if (!publicKey) throw new Error("No wallet connected.");
 
       const [investorRecordPda] = PublicKey.findProgramAddressSync(
@@ -247,24 +449,30 @@ export function useGrasschainContractSplProgram() {
         ],
         program.programId
       );
+      // Nombre, símbolo y uri los arma el programa a partir del contrato
+      const mint = getPositionMint(program.programId, contractPk, publicKey);
+      const contractData = await program.account.contract.fetch(contractPk);
+      const collectionMint = contractData.nftMint as PublicKey;
 
       const txSig = await program.methods
-        .claimNft(name, symbol, uri)
+        .claimNft()
         .accountsPartial({
           investor: publicKey,
           contract: contractPk,
           investorRecord: investorRecordPda,
-          mint: mint.publicKey,
-          associatedTokenAccount,
-          metadataAccount,
-          masterEditionAccount,
+          mint,
+          associatedTokenAccount: getAssociatedTokenAddressSync(mint, publicKey),
+          metadataAccount: getMetadataPda(mint),
+          masterEditionAccount: getMasterEditionPda(mint),
+          collectionMint,
+          collectionMetadata: getMetadataPda(collectionMint),
+          collectionMasterEdition: getMasterEditionPda(collectionMint),
           tokenProgram: TOKEN_PROGRAM_ID,
           associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
           tokenMetadataProgram: TOKEN_METADATA_PROGRAM_ID,
           systemProgram: SystemProgram.programId,
           rent: SYSVAR_RENT_PUBKEY,
         })
-        .signers([mint])
         .rpc();
 
       toast.success("claimNft success: " + txSig);
@@ -292,12 +500,117 @@ export function useGrasschainContractSplProgram() {
     },
   });
 
+  interface ExpireVerificationArgs {
+    contractPk: PublicKey;
+  }
+  const expireVerification = useMutation<string, Error, ExpireVerificationArgs>({
+    mutationFn: async ({ contractPk }) => {
+      const txSig = await program.methods
+        .expireVerification()
+        .accounts({
+          contract: contractPk,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+      toast.success("expireVerification success: " + txSig);
+      allContracts.refetch();
+      return txSig;
+    },
+  });
+
+  interface ClaimRefundArgs {
+    contractPk: PublicKey;
+    investorTokenAccount: PublicKey;
+  }
+  const claimRefund = useMutation<string, Error, ClaimRefundArgs>({
+    mutationFn: async ({ contractPk, investorTokenAccount }) => {
+      if (!publicKey) throw new Error("No wallet connected.");
+
+      const contractData = await program.account.contract.fetch(contractPk);
+      const escrowVault = contractData.escrowTokenAccount as PublicKey;
+
+      const [investorRecordPda] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("investor-record"),
+          contractPk.toBuffer(),
+          publicKey.toBuffer(),
+        ],
+        program.programId
+      );
+
+      const txSig = await program.methods
+        .claimRefund()
+        .accountsPartial({
+          contract: contractPk,
+          investor: publicKey,
+          investorRecord: investorRecordPda,
+          escrowVault,
+          investorTokenAccount,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+      toast.success("claimRefund success: " + txSig);
+      queryClient.invalidateQueries(["investorRecords", contractPk.toBase58()] as any);
+      allContracts.refetch();
+      return txSig;
+    },
+  });
+
+  function getWithdrawProposalPDA(contractPk: PublicKey) {
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from("withdraw-proposal"), contractPk.toBuffer()],
+      program.programId
+    );
+  }
+
+  interface ProposeWithdrawArgs {
+    contractPk: PublicKey;
+  }
+  const proposeWithdraw = useMutation<string, Error, ProposeWithdrawArgs>({
+    mutationFn: async ({ contractPk }) => {
+      if (!publicKey) throw new Error("No wallet connected.");
+      const [proposalPda] = getWithdrawProposalPDA(contractPk);
+
+      const txSig = await program.methods
+        .proposeWithdraw()
+        .accountsPartial({
+          contract: contractPk,
+          admin: publicKey,
+          proposal: proposalPda,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+      toast.success("proposeWithdraw success: " + txSig);
+      return txSig;
+    },
+  });
+
+  interface ApproveWithdrawArgs {
+    contractPk: PublicKey;
+  }
+  const approveWithdraw = useMutation<string, Error, ApproveWithdrawArgs>({
+    mutationFn: async ({ contractPk }) => {
+      if (!publicKey) throw new Error("No wallet connected.");
+      const [proposalPda] = getWithdrawProposalPDA(contractPk);
+
+      const txSig = await program.methods
+        .approveWithdraw()
+        .accountsPartial({
+          proposal: proposalPda,
+          signer: publicKey,
+        })
+        .rpc();
+      toast.success("approveWithdraw success: " + txSig);
+      return txSig;
+    },
+  });
+
   interface AdminWithdrawArgs {
     contractPk: PublicKey;
-    adminTokenAccount: PublicKey;
+    borrowerTokenAccount: PublicKey;
   }
   const adminWithdraw = useMutation<string, Error, AdminWithdrawArgs>({
-    mutationFn: async ({ contractPk, adminTokenAccount }) => {
+    mutationFn: async ({ contractPk, borrowerTokenAccount }) => {
       if (!publicKey) throw new Error("No wallet connected.");
 
       const contractData = await program.account.contract.fetch(contractPk);
@@ -308,8 +621,9 @@ export function useGrasschainContractSplProgram() {
         .accountsPartial({
           contract: contractPk,
           admin: publicKey,
-          adminTokenAccount,
+          borrowerTokenAccount,
           escrowVault,
+          proposal: getWithdrawProposalPDA(contractPk)[0],
           tokenProgram: TOKEN_PROGRAM_ID,
           systemProgram: SystemProgram.programId,
         })
@@ -323,23 +637,16 @@ export function useGrasschainContractSplProgram() {
 
   interface AdminCancelArgs {
     contractPk: PublicKey;
-    investorTokenAccount: PublicKey;
   }
   const adminCancel = useMutation<string, Error, AdminCancelArgs>({
-    mutationFn: async ({ contractPk, investorTokenAccount }) => {
+    mutationFn: async ({ contractPk }) => {
       if (!publicKey) throw new Error("No wallet connected.");
 
-      const contractData = await program.account.contract.fetch(contractPk);
-      const escrowVault = contractData.escrowTokenAccount as PublicKey;
-
       const txSig = await program.methods
         .adminCancel()
         .accountsPartial({
           contract: contractPk,
           admin: publicKey,
-          escrowVault,
-          investorTokenAccount,
-          tokenProgram: TOKEN_PROGRAM_ID,
           systemProgram: SystemProgram.programId,
         })
         .rpc();
@@ -367,6 +674,31 @@ export function useGrasschainContractSplProgram() {
     },
   });
 
+  interface EarlyRepayArgs {
+    contractPk: PublicKey;
+    borrowerTokenAccount: PublicKey;
+  }
+  const earlyRepay = useMutation<string, Error, EarlyRepayArgs>({
+    mutationFn: async ({ contractPk, borrowerTokenAccount }) => {
+      if (!publicKey) throw new Error("No wallet connected.");
+
+      const txSig = await program.methods
+        .earlyRepay()
+        .accountsPartial({
+          contract: contractPk,
+          borrower: publicKey,
+          tokenMint: USDC_DEVNET_MINT,
+          borrowerTokenAccount,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+      toast.success("earlyRepay success: " + txSig);
+      allContracts.refetch();
+      return txSig;
+    },
+  });
+
   interface SettleContractArgs {
     contractPk: PublicKey;
     amount: number;
@@ -374,22 +706,104 @@ export function useGrasschainContractSplProgram() {
     investorTokenAccount: PublicKey;
   }
 
-  interface ProlongContractArgs {
+  interface RequestProlongationArgs {
     contractPk: PublicKey;
+    extensionSeconds?: number;
+    sweetenerBps?: number;
   }
-  const prolongContract = useMutation<string, Error, ProlongContractArgs>({
-    mutationFn: async ({ contractPk }) => {
+  const requestProlongation = useMutation<string, Error, RequestProlongationArgs>({
+    mutationFn: async ({
+      contractPk,
+      extensionSeconds = DEFAULT_PROLONG_EXTENSION,
+      sweetenerBps = 0,
+    }) => {
       if (!publicKey) throw new Error("No wallet connected.");
 
       const txSig = await program.methods
-        .prolongContract()
+        .requestProlongation(new BN(extensionSeconds), sweetenerBps)
         .accountsPartial({
           contract: contractPk,
           admin: publicKey,
           systemProgram: SystemProgram.programId,
         })
         .rpc();
-      toast.success("prolongContract success: " + txSig);
+      toast.success("requestProlongation success: " + txSig);
+      allContracts.refetch();
+      return txSig;
+    },
+  });
+
+  interface VoteProlongationArgs {
+    contractPk: PublicKey;
+    approve: boolean;
+    // Original investor of the record; defaults to the connected wallet
+    investorPk?: PublicKey;
+  }
+  const voteProlongation = useMutation<string, Error, VoteProlongationArgs>({
+    mutationFn: async ({ contractPk, approve, investorPk }) => {
+      if (!publicKey) throw new Error("No wallet connected.");
+
+      const [requestPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("prolongation"), contractPk.toBuffer()],
+        program.programId
+      );
+      const request = await program.account.prolongationRequest.fetch(requestPda);
+
+      const recordOwner = investorPk ?? publicKey;
+      const { investorRecordPda, positionTokenAccount } = await findPositionHolder(
+        contractPk,
+        recordOwner
+      );
+      const [votePda] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("prolongation-vote"),
+          contractPk.toBuffer(),
+          recordOwner.toBuffer(),
+          new BN(request.nonce).toArrayLike(Buffer, "le", 4),
+        ],
+        program.programId
+      );
+
+      const txSig = await program.methods
+        .voteProlongation(approve)
+        .accountsPartial({
+          contract: contractPk,
+          request: requestPda,
+          voter: publicKey,
+          investorRecord: investorRecordPda,
+          positionTokenAccount,
+          vote: votePda,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+      toast.success("voteProlongation success: " + txSig);
+      return txSig;
+    },
+  });
+
+  interface FinalizeProlongationArgs {
+    contractPk: PublicKey;
+  }
+  const finalizeProlongation = useMutation<string, Error, FinalizeProlongationArgs>({
+    mutationFn: async ({ contractPk }) => {
+      if (!publicKey) throw new Error("No wallet connected.");
+
+      const [requestPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("prolongation"), contractPk.toBuffer()],
+        program.programId
+      );
+      const request = await program.account.prolongationRequest.fetch(requestPda);
+
+      const txSig = await program.methods
+        .finalizeProlongation()
+        .accountsPartial({
+          contract: contractPk,
+          request: requestPda,
+          requester: request.requester,
+          payer: publicKey,
+        })
+        .rpc();
+      toast.success("finalizeProlongation success: " + txSig);
       allContracts.refetch();
       return txSig;
     },
@@ -400,7 +814,6 @@ export function useGrasschainContractSplProgram() {
     investorRecordPk: PublicKey;
     investorPk: PublicKey;  
     adminTokenAccount: PublicKey;
-    investorTokenAccount: PublicKey;
   }
   const settleInvestor = useMutation<string, Error, SettleInvestorArgs>({
     mutationFn: async ({
@@ -408,9 +821,11 @@ export function useGrasschainContractSplProgram() {
       investorRecordPk,
       investorPk,
       adminTokenAccount,
-      investorTokenAccount,
     }) => {
       if (!publicKey || !signTransaction) throw new Error("No wallet connected.");
+      // Se paga al titular actual del NFT de la posición
+      const { positionTokenAccount, positionMint, payoutTokenAccount } =
+        await findPositionHolder(contractPk, investorPk);
 
       const ix = await program.methods
         .settleInvestor()// método recién creado en Rust
@@ -420,7 +835,9 @@ export function useGrasschainContractSplProgram() {
           investorRecord: investorRecordPk,
           investor: investorPk,    
           adminTokenAccount,
-          investorTokenAccount,
+          positionTokenAccount,
+          positionMint,
+          investorTokenAccount: payoutTokenAccount,
           tokenProgram: TOKEN_PROGRAM_ID,
         })
         .instruction();
@@ -440,15 +857,160 @@ export function useGrasschainContractSplProgram() {
   });
 
 
+  interface FundBuybackArgs {
+    contractPk: PublicKey;
+    amount: number;
+    borrowerTokenAccount: PublicKey;
+  }
+  const fundBuyback = useMutation<string, Error, FundBuybackArgs>({
+    mutationFn: async ({ contractPk, amount, borrowerTokenAccount }) => {
+      if (!publicKey) throw new Error("No wallet connected.");
+
+      const [buybackVaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("buyback-vault"), contractPk.toBuffer()],
+        program.programId
+      );
+
+      const txSig = await program.methods
+        .fundBuyback(new BN(amount))
+        .accountsPartial({
+          contract: contractPk,
+          borrower: publicKey,
+          tokenMint: USDC_DEVNET_MINT,
+          buybackVault: buybackVaultPda,
+          borrowerTokenAccount,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+      toast.success("fundBuyback success: " + txSig);
+      allContracts.refetch();
+      return txSig;
+    },
+  });
+
+  interface ClaimPayoutArgs {
+    contractPk: PublicKey;
+    // Original investor of the record; payout goes to the position holder
+    investorPk: PublicKey;
+  }
+  const claimPayout = useMutation<string, Error, ClaimPayoutArgs>({
+    mutationFn: async ({ contractPk, investorPk }) => {
+      const { investorRecordPda, positionTokenAccount, positionMint, payoutTokenAccount } =
+        await findPositionHolder(contractPk, investorPk);
+      const [buybackVaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("buyback-vault"), contractPk.toBuffer()],
+        program.programId
+      );
+
+      const txSig = await program.methods
+        .claimPayout()
+        .accountsPartial({
+          contract: contractPk,
+          investorRecord: investorRecordPda,
+          investor: investorPk,
+          buybackVault: buybackVaultPda,
+          positionTokenAccount,
+          positionMint,
+          investorTokenAccount: payoutTokenAccount,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+      toast.success("claimPayout success: " + txSig);
+      queryClient.invalidateQueries(["investorRecords", contractPk.toBase58()] as any);
+      allContracts.refetch();
+      return txSig;
+    },
+  });
+
+  interface RepayArgs {
+    contractPk: PublicKey;
+    amount: number;
+    borrowerTokenAccount: PublicKey;
+  }
+  const repay = useMutation<string, Error, RepayArgs>({
+    mutationFn: async ({ contractPk, amount, borrowerTokenAccount }) => {
+      if (!publicKey) throw new Error("No wallet connected.");
+
+      const txSig = await program.methods
+        .repay(new BN(amount))
+        .accountsPartial({
+          contract: contractPk,
+          borrower: publicKey,
+          tokenMint: USDC_DEVNET_MINT,
+          borrowerTokenAccount,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+      toast.success("repay success: " + txSig);
+      allContracts.refetch();
+      return txSig;
+    },
+  });
+
+  interface ClaimInstallmentsArgs {
+    contractPk: PublicKey;
+    // Original investor of the record; payout goes to the position holder
+    investorPk: PublicKey;
+  }
+  const claimInstallments = useMutation<string, Error, ClaimInstallmentsArgs>({
+    mutationFn: async ({ contractPk, investorPk }) => {
+      const { investorRecordPda, positionTokenAccount, positionMint, payoutTokenAccount } =
+        await findPositionHolder(contractPk, investorPk);
+
+      const txSig = await program.methods
+        .claimInstallments()
+        .accountsPartial({
+          contract: contractPk,
+          investorRecord: investorRecordPda,
+          investor: investorPk,
+          positionTokenAccount,
+          positionMint,
+          investorTokenAccount: payoutTokenAccount,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+      toast.success("claimInstallments success: " + txSig);
+      queryClient.invalidateQueries(["investorRecords", contractPk.toBase58()] as any);
+      allContracts.refetch();
+      return txSig;
+    },
+  });
+
   interface DefaultContractArgs {
     contractPk: PublicKey;
   }
   const defaultContract = useMutation<string, Error, DefaultContractArgs>({
     mutationFn: async ({ contractPk }) => {
+      if (!publicKey) throw new Error("No wallet connected.");
+
+      const contractData = await program.account.contract.fetch(contractPk);
+      const [recoveryVaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("recovery-vault"), contractPk.toBuffer()],
+        program.programId
+      );
+      const [buybackVaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("buyback-vault"), contractPk.toBuffer()],
+        program.programId
+      );
+      const sweepsBuyback =
+        !contractData.hasSchedule && !contractData.buybackFunded.isZero();
+      const [schedulePda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("schedule"), contractPk.toBuffer()],
+        program.programId
+      );
+
       const txSig = await program.methods
         .defaultContract()
-        .accounts({
+        .accountsPartial({
           contract: contractPk,
+          payer: publicKey,
+          tokenMint: contractData.tokenMint,
+          recoveryVault: recoveryVaultPda,
+          buybackVault: sweepsBuyback ? buybackVaultPda : null,
+          schedule: contractData.hasSchedule ? schedulePda : null,
+          tokenProgram: TOKEN_PROGRAM_ID,
           systemProgram: SystemProgram.programId,
         })
         .rpc();
@@ -458,6 +1020,69 @@ export function useGrasschainContractSplProgram() {
     },
   });
 
+  interface DepositRecoveryArgs {
+    contractPk: PublicKey;
+    amount: number;
+    depositorTokenAccount: PublicKey;
+  }
+  const depositRecovery = useMutation<string, Error, DepositRecoveryArgs>({
+    mutationFn: async ({ contractPk, amount, depositorTokenAccount }) => {
+      if (!publicKey) throw new Error("No wallet connected.");
+
+      const [recoveryVaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("recovery-vault"), contractPk.toBuffer()],
+        program.programId
+      );
+
+      const txSig = await program.methods
+        .depositRecovery(new BN(amount))
+        .accountsPartial({
+          contract: contractPk,
+          depositor: publicKey,
+          depositorTokenAccount,
+          recoveryVault: recoveryVaultPda,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+      toast.success("depositRecovery success: " + txSig);
+      allContracts.refetch();
+      return txSig;
+    },
+  });
+
+  interface ClaimRecoveryArgs {
+    contractPk: PublicKey;
+    // Original investor of the record; payout goes to the position holder
+    investorPk: PublicKey;
+  }
+  const claimRecovery = useMutation<string, Error, ClaimRecoveryArgs>({
+    mutationFn: async ({ contractPk, investorPk }) => {
+      const { investorRecordPda, positionTokenAccount, payoutTokenAccount } =
+        await findPositionHolder(contractPk, investorPk);
+      const [recoveryVaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("recovery-vault"), contractPk.toBuffer()],
+        program.programId
+      );
+
+      const txSig = await program.methods
+        .claimRecovery()
+        .accountsPartial({
+          contract: contractPk,
+          investorRecord: investorRecordPda,
+          investor: investorPk,
+          recoveryVault: recoveryVaultPda,
+          positionTokenAccount,
+          investorTokenAccount: payoutTokenAccount,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+      toast.success("claimRecovery success: " + txSig);
+      queryClient.invalidateQueries(["investorRecords", contractPk.toBase58()] as any);
+      allContracts.refetch();
+      return txSig;
+    },
+  });
+
   interface CloseContractArgs { contractPk: PublicKey }
 const closeContract = useMutation<string, Error, CloseContractArgs>({
   mutationFn: async ({ contractPk }) => {
@@ -475,28 +1100,195 @@ const closeContract = useMutation<string, Error, CloseContractArgs>({
   },
 });
 
+  interface SweepBuybackVaultArgs {
+    contractPk: PublicKey;
+    borrowerTokenAccount: PublicKey;
+  }
+  const sweepBuybackVault = useMutation<string, Error, SweepBuybackVaultArgs>({
+    mutationFn: async ({ contractPk, borrowerTokenAccount }) => {
+      if (!publicKey) throw new Error("No wallet connected.");
+
+      const [buybackVaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("buyback-vault"), contractPk.toBuffer()],
+        program.programId
+      );
+
+      const txSig = await program.methods
+        .sweepBuybackVault()
+        .accountsPartial({
+          contract: contractPk,
+          borrower: publicKey,
+          buybackVault: buybackVaultPda,
+          borrowerTokenAccount,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+      toast.success("sweepBuybackVault success: " + txSig);
+      return txSig;
+    },
+  });
+
+  interface ListPositionArgs {
+    contractPk: PublicKey;
+    // Original investor of the record being sold
+    investorPk: PublicKey;
+    price: number;
+  }
+  const listPosition = useMutation<string, Error, ListPositionArgs>({
+    mutationFn: async ({ contractPk, investorPk, price }) => {
+      if (!publicKey) throw new Error("No wallet connected.");
+
+      const { investorRecordPda, positionTokenAccount } = await findPositionHolder(
+        contractPk,
+        investorPk
+      );
+      if (!positionTokenAccount) throw new Error("Mint the position NFT first.");
+      const [listingPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("listing"), investorRecordPda.toBuffer()],
+        program.programId
+      );
+
+      const txSig = await program.methods
+        .listPosition(new BN(price))
+        .accountsPartial({
+          contract: contractPk,
+          investorRecord: investorRecordPda,
+          seller: publicKey,
+          sellerPositionAccount: positionTokenAccount,
+          listing: listingPda,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+      toast.success("listPosition success: " + txSig);
+      queryClient.invalidateQueries(["listings", contractPk.toBase58()] as any);
+      return txSig;
+    },
+  });
+
+  interface BuyPositionArgs {
+    contractPk: PublicKey;
+    listingPk: PublicKey;
+    buyerTokenAccount: PublicKey;
+    // Highest price the buyer accepts; the tx fails if the listing was repriced above it
+    maxPrice: number;
+  }
+  const buyPosition = useMutation<string, Error, BuyPositionArgs>({
+    mutationFn: async ({ contractPk, listingPk, buyerTokenAccount, maxPrice }) => {
+      if (!publicKey) throw new Error("No wallet connected.");
+
+      const listing = await program.account.listing.fetch(listingPk);
+      const record = await program.account.investorRecord.fetch(listing.investorRecord);
+      const contractData = await program.account.contract.fetch(contractPk);
+
+      const [configPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("config")],
+        program.programId
+      );
+      const config = await program.account.config.fetch(configPda);
+
+      const txSig = await program.methods
+        .buyPosition(new BN(maxPrice))
+        .accountsPartial({
+          contract: contractPk,
+          investorRecord: listing.investorRecord,
+          listing: listingPk,
+          seller: listing.seller,
+          buyer: publicKey,
+          positionMint: record.nftMint,
+          sellerPositionAccount: listing.positionTokenAccount,
+          buyerPositionAccount: getAssociatedTokenAddressSync(record.nftMint, publicKey),
+          buyerTokenAccount,
+          sellerTokenAccount: getAssociatedTokenAddressSync(
+            contractData.tokenMint,
+            listing.seller,
+            true
+          ),
+          feeRecipientTokenAccount: getAssociatedTokenAddressSync(
+            contractData.tokenMint,
+            config.feeRecipient,
+            true
+          ),
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+      toast.success("buyPosition success: " + txSig);
+      queryClient.invalidateQueries(["listings", contractPk.toBase58()] as any);
+      queryClient.invalidateQueries(["investorRecords", contractPk.toBase58()] as any);
+      return txSig;
+    },
+  });
+
+  interface CancelListingArgs {
+    contractPk: PublicKey;
+    listingPk: PublicKey;
+  }
+  const cancelListing = useMutation<string, Error, CancelListingArgs>({
+    mutationFn: async ({ contractPk, listingPk }) => {
+      if (!publicKey) throw new Error("No wallet connected.");
+
+      const listing = await program.account.listing.fetch(listingPk);
+
+      const txSig = await program.methods
+        .cancelListing()
+        .accountsPartial({
+          contract: contractPk,
+          listing: listingPk,
+          seller: listing.seller,
+          sellerPositionAccount: listing.positionTokenAccount,
+          caller: publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+      toast.success("cancelListing success: " + txSig);
+      queryClient.invalidateQueries(["listings", contractPk.toBase58()] as any);
+      return txSig;
+    },
+  });
 
   return {
     program,
     allContracts,
     createContract,
     investContract,
+    withdrawInvestment,
+    setWithdrawalTerms,
     claimNft,
     verifyFunding,
     expireFunding,
+    expireVerification,
+    claimRefund,
+    proposeWithdraw,
+    approveWithdraw,
     adminWithdraw,
     adminCancel,
     checkMaturity,
+    earlyRepay,
     settleInvestor,
-    prolongContract,
+    fundBuyback,
+    claimPayout,
+    repay,
+    claimInstallments,
+    requestProlongation,
+    voteProlongation,
+    finalizeProlongation,
     defaultContract,
+    depositRecovery,
+    claimRecovery,
     closeContract,
+    sweepBuybackVault,
     getInvestorRecordPDA: (contractPk: PublicKey, investorPk: PublicKey) =>
       PublicKey.findProgramAddressSync(
         [Buffer.from("investor-record"), contractPk.toBuffer(), investorPk.toBuffer()],
         program.programId
       ),
     useInvestorRecords,
+    useListings,
+    listPosition,
+    buyPosition,
+    cancelListing,
     // Helper para generar CSV usando los nombres correctos (camelCase)
     generateInvestorRecordsCSV: (records: any): string => {
       if (!records) return "";
@@ -2,7 +2,7 @@
 
 import React, { useState, useEffect } from "react";
 import { createPortal } from "react-dom";
-import { PublicKey, Transaction, Keypair, SystemProgram, SendTransactionError } from "@solana/web3.js";
+import { PublicKey, Transaction, SystemProgram, SendTransactionError } from "@solana/web3.js";
 import { useConnection, useWallet } from "@solana/wallet-adapter-react";
 import { BN } from "@coral-xyz/anchor";
 import {
@@ -13,7 +13,7 @@ import {
 import {
   useGrasschainContractSplProgram,
   USDC_DEVNET_MINT,
-  TOKEN_METADATA_PROGRAM_ID,
+  getPositionMint,
 } from "./grasschain_contract_spl-data-access";
 import toast from "react-hot-toast";
 import { useSession } from "next-auth/react";
@@ -23,31 +23,7 @@ import { upload } from "@vercel/blob/client";
 import { isExternal } from "util/types";
 
 
-// Helpers to derive PDA for metadata and master edition
-function getMetadataPDA(mint: PublicKey): [PublicKey, number] {
-  return PublicKey.findProgramAddressSync(
-    [
-      Buffer.from("metadata"),
-      TOKEN_METADATA_PROGRAM_ID.toBuffer(),
-      mint.toBuffer(),
-    ],
-    TOKEN_METADATA_PROGRAM_ID
-  );
-}
-
-function getMasterEditionPDA(mint: PublicKey): [PublicKey, number] {
-  return PublicKey.findProgramAddressSync(
-    [
-      Buffer.from("metadata"),
-      TOKEN_METADATA_PROGRAM_ID.toBuffer(),
-      mint.toBuffer(),
-      Buffer.from("edition"),
-    ],
-    TOKEN_METADATA_PROGRAM_ID
-  );
-}
-
-// Must match ADMIN_ADDRESS in lib.rs
+// Must match Config.admin on-chain (see initialize_config / accept_admin)
 const ADMIN_PUBKEY = "74bwEVrLxoWtg8ya7gB1KKKuff9wnNADys1Ss1cxsEdd";
 
 export function GrasschainCreateContractForm() {
@@ -69,7 +45,8 @@ export function GrasschainCreateContractForm() {
       return;
     }
     const i = parseFloat(investment) * 1_000_000;
-    const y = parseInt(yieldPerc, 10);
+    // APR en % => basis points (7.5% => 750)
+    const y = Math.round(parseFloat(yieldPerc) * 100);
     const minutes = parseInt(termMinutes, 10);
     const d = minutes * 60; 
     if (isNaN(i) || isNaN(y) || isNaN(minutes)) {
@@ -79,9 +56,8 @@ export function GrasschainCreateContractForm() {
     const nowSec = Math.floor(Date.now() / 1000);
 
     await createContract.mutateAsync({
-      nftMint: new PublicKey("11111111111111111111111111111111"), // dummy NFT mint
       totalInvestmentNeeded: i,
-      yieldPercentage: y,
+      aprBps: y,
       durationInSeconds: d,
       contractId: nowSec,
       farmName,
@@ -107,7 +83,7 @@ export function GrasschainCreateContractForm() {
         value={investment}
         onChange={(e) => setInvestment(e.target.value)}
       />
-      <label className="block mb-1">Yield Percentage (%)</label>
+      <label className="block mb-1">Annual Yield (APR %)</label>
       <input
         type="number"
         className="input input-bordered w-full mb-3 bg-black text-white"
@@ -181,7 +157,7 @@ export function GrasschainContractCard({
     checkMaturity,
     verifyFunding,
     settleInvestor,
-    prolongContract,
+    requestProlongation,
     defaultContract,
     closeContract,
     useInvestorRecords,
@@ -205,9 +181,12 @@ export function GrasschainContractCard({
     }
   }, [investorsLoading, investorRecords, contractPk]);
 
+  const myRecord = publicKey
+    ? investorRecords?.find((r) => r.account.investor.equals(publicKey))
+    : undefined;
+
   // ─── LOCAL STATE ───
   const [investInput, setInvestInput] = useState("");
-  const [hasInvested, setHasInvested] = useState(false);
 
   const {
     all: notes,
@@ -243,6 +222,11 @@ export function GrasschainContractCard({
   else if ("settled" in contractData.status) status = "Settled";
   else if ("defaulted" in contractData.status) status = "Defaulted";
   else if ("cancelled" in contractData.status) status = "Cancelled";
+  // Shown to users while "Funded Pending Verification" drives the admin buttons
+  const statusLabel =
+    status === "Funded Pending Verification" && contractData.verified
+      ? "Verified"
+      : status;
 
   const totalNeeded = contractData.totalInvestmentNeeded.toNumber() / 1_000_000;
   const onChainFunded = contractData.amountFundedSoFar / 1_000_000;
@@ -273,7 +257,7 @@ export function GrasschainContractCard({
     deadlineLabel = "Deadline";
   } else if ("fundedPendingVerification" in contractData.status) {
     endDate = new Date(
-      (contractData.fundedTime.toNumber() + 30 * 86400) * 1000
+      (contractData.fundedTime.toNumber() + contractData.adminWindow.toNumber()) * 1000
     );
     deadlineLabel = "Verification Deadline";
   } else if ("active" in contractData.status) {
@@ -322,10 +306,7 @@ export function GrasschainContractCard({
         return;
       }
 
-      // 1) Generate NFT mint first
-      const mintKeypair = Keypair.generate();
-
-      // 2) transfer USDC -> escrow on-chain
+      // 1) transfer USDC -> escrow on-chain
       const userAta = await getAssociatedTokenAddress(
         USDC_DEVNET_MINT,
         publicKey,
@@ -338,40 +319,20 @@ export function GrasschainContractCard({
         investorTokenAccount: userAta,
       });
 
-      // 3) record it off-chain in Mongo
+      // 2) record it off-chain in Mongo; the position NFT (claimable once the
+      //    contract is Active) lives at a fixed PDA per investor record
       await fetch("/api/crypto-investor", {
         method: "POST",
         headers: { "Content-Type": "application/json" },
         body: JSON.stringify({
           contract:    contractPk.toBase58(),
           investor:    publicKey.toBase58(),
-          nftMint:     mintKeypair.publicKey.toBase58(),
+          nftMint:     getPositionMint(program.programId, contractPk, publicKey).toBase58(),
           txSignature: txSig,
           amount:      amount / 1e6,                     // store in USDC units
         }),
       });
 
-      // 4) now mint & claim NFT…
-      const [metaPda]    = getMetadataPDA(mintKeypair.publicKey);
-      const [editionPda] = getMasterEditionPDA(mintKeypair.publicKey);
-      const nftAta       = await getAssociatedTokenAddress(
-        mintKeypair.publicKey,
-        publicKey,
-        false,
-        TOKEN_PROGRAM_ID
-      );
-      await claimNft.mutateAsync({
-        contractPk,
-        mint:                  mintKeypair,
-        associatedTokenAccount: nftAta,
-        metadataAccount:       metaPda,
-        masterEditionAccount:  editionPda,
-        name:                  "Pastora NFT",
-        symbol:                "PTORA",
-        uri:                   "https://app.pastora.io/tokenMetadata.json",
-      });
-
-      setHasInvested(true);
       setInvestInput("");
       return;
     }
@@ -415,25 +376,7 @@ export function GrasschainContractCard({
       alert("Connect your wallet first.");
       return;
     }
-    const mintKeypair = Keypair.generate();
-    const nftAta = await getAssociatedTokenAddress(
-      mintKeypair.publicKey,
-      publicKey,
-      false,
-      TOKEN_PROGRAM_ID
-    );
-    const [metadataPDA] = getMetadataPDA(mintKeypair.publicKey);
-    const [masterEditionPDA] = getMasterEditionPDA(mintKeypair.publicKey);
-    await claimNft.mutateAsync({
-      contractPk,
-      mint: mintKeypair,
-      associatedTokenAccount: nftAta,
-      metadataAccount: metadataPDA,
-      masterEditionAccount: masterEditionPDA,
-      name: "Pastora NFT",
-      symbol: "PTORA",
-      uri: "https://app.pastora.io/tokenMetadata.json",
-    });
+    await claimNft.mutateAsync({ contractPk });
   }
 
   async function handleAdminWithdraw() {
@@ -441,13 +384,13 @@ export function GrasschainContractCard({
       alert("Connect as admin.");
       return;
     }
-    const adminAta = await getAssociatedTokenAddress(
+    const borrowerAta = await getAssociatedTokenAddress(
       USDC_DEVNET_MINT,
-      publicKey,
+      contractData.borrower,
       false,
       TOKEN_PROGRAM_ID
     );
-    await adminWithdraw.mutateAsync({ contractPk, adminTokenAccount: adminAta });
+    await adminWithdraw.mutateAsync({ contractPk, borrowerTokenAccount: borrowerAta });
   }
 
   async function handleAdminCancel() {
@@ -455,13 +398,7 @@ export function GrasschainContractCard({
       alert("Connect as admin.");
       return;
     }
-    const adminAta = await getAssociatedTokenAddress(
-      USDC_DEVNET_MINT,
-      publicKey,
-      false,
-      TOKEN_PROGRAM_ID
-    );
-    await adminCancel.mutateAsync({ contractPk, investorTokenAccount: adminAta });
+    await adminCancel.mutateAsync({ contractPk });
   }
 
   async function handleCheckMaturity() {
@@ -493,12 +430,6 @@ export function GrasschainContractCard({
     // 2) Para cada inversor, llama settleInvestor
     for (const rec of investorRecords) {
       const investorPk = new PublicKey(rec.account.investor);
-      const investorAta = await getAssociatedTokenAddress(
-        USDC_DEVNET_MINT,
-        investorPk,
-        false,
-        TOKEN_PROGRAM_ID
-      );
       // PDA del InvestorRecord
       const [recordPda] = PublicKey.findProgramAddressSync(
         [
@@ -516,12 +447,17 @@ export function GrasschainContractCard({
           investorRecordPk: recordPda,
           investorPk: investorPk,
           adminTokenAccount: adminAta,
-          investorTokenAccount: investorAta,
         });
         toast.success(
           `Pagado ${(
             rec.account.amount.toNumber() +
-            Math.floor((rec.account.amount.toNumber() * contractData.yieldPercentage.toNumber()) / 100)
+            Math.floor(
+              (rec.account.amount.toNumber() *
+                contractData.aprBps *
+                (Math.min(Date.now() / 1000, contractData.buybackDeadline.toNumber()) -
+                  contractData.startTime.toNumber())) /
+                (10_000 * 365 * 86400)
+            )
           ) / 1_000_000} USDC a ${investorPk.toBase58()}`
         );
       } catch (err: any) {
@@ -547,7 +483,7 @@ export function GrasschainContractCard({
       alert("Connect as admin.");
       return;
     }
-    await prolongContract.mutateAsync({ contractPk });
+    await requestProlongation.mutateAsync({ contractPk });
   }
 
   async function handleDefault() {
@@ -589,7 +525,7 @@ export function GrasschainContractCard({
         {/* Right Side: Contract Details */}
         <div className="w-full p-6">
         <span className="absolute top-3 left-3 px-3 py-2 text-sm bg-green-600 uppercase rounded-2xl text-white font-bold z-50">
-            {status}
+            {statusLabel}
           </span>
           {/* Farm Name as title */}
           <h3 className="text-3xl font-bold mb-4 text-center md:text-4xl">{farmNameText}</h3>
@@ -611,7 +547,7 @@ export function GrasschainContractCard({
             {/* Right Column: Yield Percentage */}
             <div className="flex items-center justify-center md:justify-end">
               <div className="text-6xl md:text-7xl font-extrabold text-gray-800">
-                {contractData.yieldPercentage.toString()}%
+                {(contractData.aprBps / 100).toString()}% APR
               </div>
             </div>
           </div>
@@ -660,22 +596,29 @@ export function GrasschainContractCard({
               >
                 Invest
               </button>
-              {hasInvested && (
-                <button
-                  className="btn btn-primary w-full"
-                  onClick={handleClaimNft}
-                >
-                  Mint NFT
-                </button>
-              )}
             </div>
           )}
+          {["Active", "Pending Buyback", "Prolonged"].includes(status) &&
+            myRecord &&
+            !myRecord.account.nftMinted &&
+            !myRecord.account.amount.isZero() && (
+              <button
+                className="btn btn-primary w-full"
+                onClick={handleClaimNft}
+              >
+                Mint NFT
+              </button>
+            )}
         </div>
       </div>
       {/* Admin Actions and Download CSV */}
       {publicKey?.toBase58() === ADMIN_PUBKEY && (
         <div className="mt-4 flex flex-col space-y-2 px-4 pb-4">
-        {status === "Funding" && (onChainFunded + fiatFunded) >= totalNeeded && (
+        {((status === "Funded Pending Verification" && !contractData.verified) ||
+          (status === "Funding" &&
+            Date.now() > contractData.fundingDeadline.toNumber() * 1000 &&
+            !contractData.minimumRaise.isZero() &&
+            contractData.amountFundedSoFar.gte(contractData.minimumRaise))) && (
            <button
              className="btn btn-primary w-full"
              onClick={() => verifyFunding.mutate({ contractPk })}
@@ -686,12 +629,14 @@ export function GrasschainContractCard({
          )}
           {status === "Funded Pending Verification" && (
             <>
+              {contractData.verified && (
               <button
                 className="btn btn-success"
                 onClick={handleAdminWithdraw}
               >
                 Withdraw Funds
               </button>
+              )}
               <button className="btn btn-error" onClick={handleAdminCancel}>
                 Cancel Contract
               </button>
@@ -710,16 +655,14 @@ export function GrasschainContractCard({
               <button className="btn btn-accent" onClick={handleSettle} disabled={investorsLoading}>
                 Settle Contract
               </button>
-              {status === "Pending Buyback" && (
+              {(status === "Pending Buyback" || status === "Prolonged") && (
                 <button className="btn btn-info" onClick={handleProlong}>
                   Request 2-Week Extension
                 </button>
               )}
-              {status === "Prolonged" && (
-                <button className="btn btn-danger" onClick={handleDefault}>
-                  Mark as Defaulted
-                </button>
-              )}
+              <button className="btn btn-danger" onClick={handleDefault}>
+                Mark as Defaulted
+              </button>
             </div>
           )}
           {/* Admin Download CSV Button */}